@@ -13,10 +13,344 @@
 // limitations under the License.
 
 use super::pb;
-use pb::BtcCoin;
+use super::Error;
+use pb::{BtcCoin, BtcOutputType};
 
+use crate::workflow::confirm;
 use util::bip32::HARDENED;
 
+pub mod bech32 {
+    //! Bech32/bech32m encoding and decoding (BIP-173 / BIP-350), used to
+    //! derive and validate witness program addresses (`bc1...`).
+    //!
+    //! The only difference between the two variants is the constant the
+    //! checksum polymod is finalized against: bech32 (witness v0) uses `1`,
+    //! while bech32m (witness v1 and up, i.e. Taproot) uses `0x2bc830a3`.
+
+    const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+    /// Checksum constant for witness v0 (P2WPKH/P2WSH), per BIP-173.
+    const BECH32_CONST: u32 = 1;
+    /// Checksum constant for witness v1..=16 (e.g. Taproot), per BIP-350.
+    const BECH32M_CONST: u32 = 0x2bc830a3;
+
+    /// Picks the checksum constant for a given witness version.
+    fn const_for_witness_version(witness_version: u8) -> u32 {
+        if witness_version == 0 {
+            BECH32_CONST
+        } else {
+            BECH32M_CONST
+        }
+    }
+
+    fn polymod(values: &[u8]) -> u32 {
+        const GEN: [u32; 5] = [
+            0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3,
+        ];
+        let mut chk: u32 = 1;
+        for &v in values {
+            let b = (chk >> 25) as u8;
+            chk = (chk & 0x1ffffff) << 5 ^ (v as u32);
+            for i in 0..5 {
+                if (b >> i) & 1 != 0 {
+                    chk ^= GEN[i];
+                }
+            }
+        }
+        chk
+    }
+
+    fn hrp_expand(hrp: &str) -> alloc::vec::Vec<u8> {
+        let mut v: alloc::vec::Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+        v.push(0);
+        v.extend(hrp.bytes().map(|b| b & 31));
+        v
+    }
+
+    /// Converts `data` (groups of `frombits` bits) into groups of `tobits` bits.
+    fn convert_bits(data: &[u8], frombits: u32, tobits: u32, pad: bool) -> Option<alloc::vec::Vec<u8>> {
+        let mut acc: u32 = 0;
+        let mut bits: u32 = 0;
+        let mut ret = alloc::vec::Vec::new();
+        let maxv: u32 = (1 << tobits) - 1;
+        for &value in data {
+            if (value as u32) >> frombits != 0 {
+                return None;
+            }
+            acc = (acc << frombits) | value as u32;
+            bits += frombits;
+            while bits >= tobits {
+                bits -= tobits;
+                ret.push(((acc >> bits) & maxv) as u8);
+            }
+        }
+        if pad {
+            if bits > 0 {
+                ret.push(((acc << (tobits - bits)) & maxv) as u8);
+            }
+        } else if bits >= frombits || ((acc << (tobits - bits)) & maxv) != 0 {
+            return None;
+        }
+        Some(ret)
+    }
+
+    /// Encodes a witness program (`witness_version`, `program`) under `hrp` as a
+    /// bech32 (v0) or bech32m (v1..=16) address, per BIP-173/BIP-350.
+    pub fn encode(hrp: &str, witness_version: u8, program: &[u8]) -> Option<alloc::string::String> {
+        if witness_version > 16 {
+            return None;
+        }
+        // Mirror `decode`'s witness program length check: only 20 (v0
+        // P2WPKH) or 32 (v0 P2WSH, v1 Taproot) bytes are valid, per BIP-141
+        // and BIP-350.
+        if !matches!(program.len(), 20 | 32) {
+            return None;
+        }
+        let mut data = alloc::vec![witness_version];
+        data.extend(convert_bits(program, 8, 5, true)?);
+
+        let const_ = const_for_witness_version(witness_version);
+        let mut values = hrp_expand(hrp);
+        values.extend(&data);
+        values.extend(&[0u8; 6]);
+        let polymod_value = polymod(&values) ^ const_;
+        let mut checksum = [0u8; 6];
+        for (i, c) in checksum.iter_mut().enumerate() {
+            *c = ((polymod_value >> (5 * (5 - i))) & 31) as u8;
+        }
+
+        let mut result = alloc::string::String::from(hrp);
+        result.push('1');
+        for &d in data.iter().chain(checksum.iter()) {
+            result.push(CHARSET[d as usize] as char);
+        }
+        Some(result)
+    }
+
+    /// Decodes and validates a bech32/bech32m address, returning the witness
+    /// version and program on success. Returns `None` if the checksum does
+    /// not match the constant expected for the parsed witness version (e.g. a
+    /// bech32 checksum on a v1+ program, or vice versa).
+    pub fn decode(hrp: &str, address: &str) -> Option<(u8, alloc::vec::Vec<u8>)> {
+        // BIP-173: an address is entirely lowercase or entirely uppercase;
+        // reject a mix instead of silently normalizing it away.
+        if address.bytes().any(|b| b.is_ascii_uppercase())
+            && address.bytes().any(|b| b.is_ascii_lowercase())
+        {
+            return None;
+        }
+        let pos = address.rfind('1')?;
+        let (addr_hrp, data_part) = address.split_at(pos);
+        if addr_hrp.to_ascii_lowercase() != hrp.to_ascii_lowercase() {
+            return None;
+        }
+        let data_part = &data_part[1..];
+        if data_part.len() < 6 {
+            return None;
+        }
+        let mut data = alloc::vec::Vec::with_capacity(data_part.len());
+        for c in data_part.bytes() {
+            let v = CHARSET.iter().position(|&x| x == c.to_ascii_lowercase() as u8)?;
+            data.push(v as u8);
+        }
+        let witness_version = *data.first()?;
+        if witness_version > 16 {
+            return None;
+        }
+        let (payload, checksum) = data.split_at(data.len() - 6);
+        let mut values = hrp_expand(hrp);
+        values.extend(&data);
+        let expected_const = const_for_witness_version(witness_version);
+        if polymod(&values) != expected_const {
+            return None;
+        }
+        let _ = checksum;
+        let (_witness_version_field, program_data) = payload.split_first()?;
+        let program = convert_bits(program_data, 5, 8, false)?;
+        match program.len() {
+            20 | 32 => Some((witness_version, program)),
+            _ => None,
+        }
+    }
+}
+
+pub mod cashaddr {
+    //! CashAddr encoding and decoding, as used by Bitcoin Cash instead of
+    //! base58check/bech32. See
+    //! https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/cashaddr.md
+    //!
+    //! The payload is `version_byte ++ hash`, where the version byte packs
+    //! the address type (0 = P2PKH, 1 = P2SH) into the upper bits and the
+    //! hash size into the lower bits. The payload is converted from 8-bit to
+    //! 5-bit groups, prefixed with the expanded human-readable prefix plus a
+    //! zero separator, and protected by a 40-bit BCH checksum.
+
+    const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+    const GEN: [u64; 5] = [
+        0x98f2bc8e61,
+        0x79b76d99e2,
+        0xf33e5fb3c4,
+        0xae2eabe2a8,
+        0x1e4f43e470,
+    ];
+
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum AddressType {
+        P2pkh,
+        P2sh,
+    }
+
+    fn polymod(values: &[u8]) -> u64 {
+        let mut c: u64 = 1;
+        for &d in values {
+            let c0 = (c >> 35) as u8;
+            c = ((c & 0x07_ffff_ffff) << 5) ^ (d as u64);
+            for i in 0..5 {
+                if (c0 >> i) & 1 != 0 {
+                    c ^= GEN[i];
+                }
+            }
+        }
+        c ^ 1
+    }
+
+    fn prefix_expand(prefix: &str) -> alloc::vec::Vec<u8> {
+        let mut v: alloc::vec::Vec<u8> = prefix.bytes().map(|b| b & 0x1f).collect();
+        v.push(0);
+        v
+    }
+
+    fn convert_bits(data: &[u8], frombits: u32, tobits: u32, pad: bool) -> Option<alloc::vec::Vec<u8>> {
+        let mut acc: u32 = 0;
+        let mut bits: u32 = 0;
+        let mut ret = alloc::vec::Vec::new();
+        let maxv: u32 = (1 << tobits) - 1;
+        for &value in data {
+            if (value as u32) >> frombits != 0 {
+                return None;
+            }
+            acc = (acc << frombits) | value as u32;
+            bits += frombits;
+            while bits >= tobits {
+                bits -= tobits;
+                ret.push(((acc >> bits) & maxv) as u8);
+            }
+        }
+        if pad {
+            if bits > 0 {
+                ret.push(((acc << (tobits - bits)) & maxv) as u8);
+            }
+        } else if bits >= frombits || ((acc << (tobits - bits)) & maxv) != 0 {
+            return None;
+        }
+        Some(ret)
+    }
+
+    fn size_class(hash_len: usize) -> Option<u8> {
+        match hash_len {
+            20 => Some(0),
+            24 => Some(1),
+            28 => Some(2),
+            32 => Some(3),
+            40 => Some(4),
+            48 => Some(5),
+            56 => Some(6),
+            64 => Some(7),
+            _ => None,
+        }
+    }
+
+    fn hash_len_for_size_class(size_class: u8) -> Option<usize> {
+        match size_class {
+            0 => Some(20),
+            1 => Some(24),
+            2 => Some(28),
+            3 => Some(32),
+            4 => Some(40),
+            5 => Some(48),
+            6 => Some(56),
+            7 => Some(64),
+            _ => None,
+        }
+    }
+
+    /// Encodes `hash` (a P2PKH or P2SH hash) as a CashAddr under `prefix`
+    /// (e.g. `"bitcoincash"`/`"bchtest"`), without the `prefix:` part.
+    pub fn encode(prefix: &str, address_type: AddressType, hash: &[u8]) -> Option<alloc::string::String> {
+        let size_class = size_class(hash.len())?;
+        let version_byte = ((address_type as u8) << 3) | size_class;
+
+        let mut payload = alloc::vec![version_byte];
+        payload.extend_from_slice(hash);
+        let payload5 = convert_bits(&payload, 8, 5, true)?;
+
+        let mut checksum_input = prefix_expand(prefix);
+        checksum_input.extend(&payload5);
+        checksum_input.extend(&[0u8; 8]);
+        let checksum_value = polymod(&checksum_input);
+        let checksum: alloc::vec::Vec<u8> = (0..8)
+            .map(|i| ((checksum_value >> (5 * (7 - i))) & 31) as u8)
+            .collect();
+
+        let mut result = alloc::string::String::new();
+        for &d in payload5.iter().chain(checksum.iter()) {
+            result.push(CHARSET[d as usize] as char);
+        }
+        Some(result)
+    }
+
+    /// Decodes and validates a CashAddr payload (without the `prefix:` part)
+    /// against the expected human-readable `prefix`.
+    pub fn decode(prefix: &str, payload: &str) -> Option<(AddressType, alloc::vec::Vec<u8>)> {
+        if payload.len() < 8 {
+            return None;
+        }
+        let mut data = alloc::vec::Vec::with_capacity(payload.len());
+        for c in payload.bytes() {
+            let v = CHARSET.iter().position(|&x| x == c.to_ascii_lowercase())?;
+            data.push(v as u8);
+        }
+        let mut checksum_input = prefix_expand(prefix);
+        checksum_input.extend(&data);
+        if polymod(&checksum_input) != 0 {
+            return None;
+        }
+        let payload5 = &data[..data.len() - 8];
+        let payload8 = convert_bits(payload5, 5, 8, false)?;
+        let (version_byte, hash) = payload8.split_first()?;
+        let address_type = match version_byte >> 3 {
+            0 => AddressType::P2pkh,
+            1 => AddressType::P2sh,
+            _ => return None,
+        };
+        if hash_len_for_size_class(version_byte & 0x07) != Some(hash.len()) {
+            return None;
+        }
+        Some((address_type, hash.to_vec()))
+    }
+}
+
+/// Derives and formats the address for a segwit transaction output, for
+/// confirmation on the trusted display. `payload` is the witness program:
+/// the 20-byte pubkey hash for [`BtcOutputType::P2wpkh`], or the 32-byte
+/// taproot output key (BIP-341) for [`BtcOutputType::P2tr`].
+///
+/// [`BtcOutputType::P2pkh`]/[`BtcOutputType::P2wpkhP2sh`] are base58check
+/// addresses, formatted by the existing base58 address layer rather than
+/// here; passing one of those (or `Unknown`) returns `None`.
+pub fn encode_segwit_address(
+    output_type: BtcOutputType,
+    coin: &dyn CoinParams,
+    payload: &[u8],
+) -> Option<alloc::string::String> {
+    let witness_version = match output_type {
+        BtcOutputType::P2wpkh => 0,
+        BtcOutputType::P2tr => 1,
+        BtcOutputType::P2pkh | BtcOutputType::P2wpkhP2sh | BtcOutputType::Unknown => return None,
+    };
+    bech32::encode(coin.bech32_hrp(), witness_version, payload)
+}
+
 /// Parameters for BTC-like coins. See also:
 /// https://en.bitcoin.it/wiki/List_of_address_prefixes
 pub struct Params {
@@ -25,9 +359,19 @@ pub struct Params {
     pub base58_version_p2pkh: u8,
     pub base58_version_p2sh: u8,
     pub bech32_hrp: &'static str,
+    /// CashAddr human-readable prefix (e.g. `"bitcoincash"`). Empty for
+    /// coins that don't use CashAddr.
+    pub cashaddr_prefix: &'static str,
     pub name: &'static str,
     pub unit: &'static str,
     pub rbf_support: bool,
+    /// Number of decimal places used to format base-unit amounts for
+    /// confirmation on the trusted display, e.g. 8 for BTC (amounts are in
+    /// satoshis).
+    pub decimals: u8,
+    /// Minimal output amount (in base units) considered non-dust, or `0` if
+    /// the coin has no dust policy worth enforcing on-device.
+    pub dust_limit: u64,
 }
 
 /// Keep these in sync with btc_params.c.
@@ -37,9 +381,12 @@ const PARAMS_BTC: Params = Params {
     base58_version_p2pkh: 0x00, // starts with 1
     base58_version_p2sh: 0x05,  // starts with 3
     bech32_hrp: "bc",
+    cashaddr_prefix: "",
     name: "Bitcoin",
     unit: "BTC",
     rbf_support: true,
+    decimals: 8,
+    dust_limit: 546,
 };
 
 const PARAMS_TBTC: Params = Params {
@@ -47,9 +394,12 @@ const PARAMS_TBTC: Params = Params {
     base58_version_p2pkh: 0x6f, // starts with m or n
     base58_version_p2sh: 0xc4,  // starts with 2
     bech32_hrp: "tb",
+    cashaddr_prefix: "",
     name: "BTC Testnet",
     unit: "TBTC",
     rbf_support: true,
+    decimals: 8,
+    dust_limit: 546,
 };
 
 const PARAMS_LTC: Params = Params {
@@ -57,9 +407,12 @@ const PARAMS_LTC: Params = Params {
     base58_version_p2pkh: 0x30, // starts with L
     base58_version_p2sh: 0x32,  // starts with M
     bech32_hrp: "ltc",
+    cashaddr_prefix: "",
     name: "Litecoin",
     unit: "LTC",
     rbf_support: false,
+    decimals: 8,
+    dust_limit: 546,
 };
 
 const PARAMS_TLTC: Params = Params {
@@ -67,9 +420,41 @@ const PARAMS_TLTC: Params = Params {
     base58_version_p2pkh: 0x6f, // starts with m or n
     base58_version_p2sh: 0xc4,  // starts with 2
     bech32_hrp: "tltc",
+    cashaddr_prefix: "",
     name: "LTC Testnet",
     unit: "TLTC",
     rbf_support: false,
+    decimals: 8,
+    dust_limit: 546,
+};
+
+// Bitcoin Cash has no native bech32 witness programs; `bech32_hrp` is unused
+// and kept empty. Addresses are formatted exclusively via `cashaddr_prefix`.
+
+const PARAMS_BCH: Params = Params {
+    bip44_coin: 145 + HARDENED,
+    base58_version_p2pkh: 0x00,
+    base58_version_p2sh: 0x05,
+    bech32_hrp: "",
+    cashaddr_prefix: "bitcoincash",
+    name: "Bitcoin Cash",
+    unit: "BCH",
+    rbf_support: false,
+    decimals: 8,
+    dust_limit: 546,
+};
+
+const PARAMS_TBCH: Params = Params {
+    bip44_coin: 1 + HARDENED,
+    base58_version_p2pkh: 0x6f,
+    base58_version_p2sh: 0xc4,
+    bech32_hrp: "",
+    cashaddr_prefix: "bchtest",
+    name: "BCH Testnet",
+    unit: "TBCH",
+    rbf_support: false,
+    decimals: 8,
+    dust_limit: 546,
 };
 
 pub fn get(coin: BtcCoin) -> &'static Params {
@@ -79,5 +464,620 @@ pub fn get(coin: BtcCoin) -> &'static Params {
         Tbtc => &PARAMS_TBTC,
         Ltc => &PARAMS_LTC,
         Tltc => &PARAMS_TLTC,
+        // See the `BCH`/`TBCH` entries in `messages/btc.proto`'s `BTCCoin` enum.
+        Bch => &PARAMS_BCH,
+        Tbch => &PARAMS_TBCH,
+    }
+}
+
+/// Host-supplied parameters for a BTC-like altcoin that isn't one of the
+/// built-in, audited coins returned by [`get`]. This lets the host add
+/// support for new coins (e.g. Dogecoin, Dash, Groestlcoin) without a
+/// firmware release, at the cost of the host being able to claim anything it
+/// likes for `name`/`unit` — every caller that signs against a `CustomParams`
+/// MUST prominently display `name` and `unit` as untrusted, host-supplied
+/// values so the user isn't tricked into signing a transaction under a
+/// spoofed coin identity.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CustomParams {
+    pub bip44_coin: u32,
+    pub base58_version_p2pkh: u8,
+    pub base58_version_p2sh: u8,
+    pub bech32_hrp: alloc::string::String,
+    pub name: alloc::string::String,
+    pub unit: alloc::string::String,
+    pub rbf_support: bool,
+    pub decimals: u8,
+    pub dust_limit: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CustomParamsError {
+    /// `bip44_coin` is not a hardened index.
+    CoinNotHardened,
+    /// `base58_version_p2pkh` and `base58_version_p2sh` must differ, or a
+    /// P2PKH and a P2SH address would be indistinguishable.
+    ConflictingBase58Versions,
+    /// `bech32_hrp` contains characters outside the allowed bech32 range, or
+    /// is uppercase (bech32 HRPs must be all-lowercase or all-uppercase, and
+    /// we only accept lowercase here).
+    InvalidBech32Hrp,
+    /// `name` or `unit` is empty.
+    EmptyNameOrUnit,
+    /// `decimals` is implausibly large for a base-unit amount display.
+    DecimalsTooLarge,
+    /// A protobuf field that narrows into a smaller integer type
+    /// (`base58_version_p2pkh`/`base58_version_p2sh` into `u8`, `decimals`
+    /// into `u8`) doesn't fit, and would otherwise silently wrap instead of
+    /// being rejected.
+    FieldOutOfRange,
+}
+
+impl CustomParams {
+    /// Validates that the host-supplied parameters are internally
+    /// consistent. This does not and cannot verify that the parameters
+    /// actually describe any real coin; it only rules out values that would
+    /// be unsafe or nonsensical to derive addresses and display amounts
+    /// with.
+    pub fn validate(&self) -> Result<(), CustomParamsError> {
+        if self.bip44_coin & HARDENED == 0 {
+            return Err(CustomParamsError::CoinNotHardened);
+        }
+        if self.base58_version_p2pkh == self.base58_version_p2sh {
+            return Err(CustomParamsError::ConflictingBase58Versions);
+        }
+        if self.name.is_empty() || self.unit.is_empty() {
+            return Err(CustomParamsError::EmptyNameOrUnit);
+        }
+        if self.decimals > 18 {
+            return Err(CustomParamsError::DecimalsTooLarge);
+        }
+        // Bech32 HRPs are composed of 1-83 US-ASCII characters in the range
+        // 33-126, and must be lowercase here (BIP-173).
+        if !self.bech32_hrp.is_empty()
+            && (self.bech32_hrp.len() > 83
+                || !self
+                    .bech32_hrp
+                    .bytes()
+                    .all(|b| (33..=126).contains(&b) && !b.is_ascii_uppercase()))
+        {
+            return Err(CustomParamsError::InvalidBech32Hrp);
+        }
+        Ok(())
+    }
+
+    /// Builds the (unvalidated, aside from the range checks needed to
+    /// narrow into this struct's field types) host-supplied parameters from
+    /// the corresponding protobuf message. Callers MUST still call
+    /// [`Self::validate`] and [`warn_untrusted`] before signing or deriving
+    /// addresses against the result.
+    pub fn from_pb(pb: &pb::BtcCoinParams) -> Result<Self, CustomParamsError> {
+        // These fields narrow from the proto's `uint32` into `u8`; check the
+        // raw range *before* casting, so e.g. a `decimals` of 274 is rejected
+        // instead of silently wrapping to 18 and passing `validate`'s
+        // `decimals > 18` check.
+        if pb.base58_version_p2pkh > u8::MAX as u32
+            || pb.base58_version_p2sh > u8::MAX as u32
+            || pb.decimals > u8::MAX as u32
+        {
+            return Err(CustomParamsError::FieldOutOfRange);
+        }
+        Ok(CustomParams {
+            bip44_coin: pb.bip44_coin,
+            base58_version_p2pkh: pb.base58_version_p2pkh as u8,
+            base58_version_p2sh: pb.base58_version_p2sh as u8,
+            bech32_hrp: pb.bech32_hrp.clone(),
+            name: pb.name.clone(),
+            unit: pb.unit.clone(),
+            rbf_support: pb.rbf_support,
+            decimals: pb.decimals as u8,
+            dust_limit: pb.dust_limit,
+        })
+    }
+}
+
+/// Warns that `params.name()`/`params.unit()` are supplied by the host and
+/// not verified by the device. Every caller that signs against a
+/// [`CustomParams`] (as opposed to the built-in, audited [`Params`]) MUST
+/// show this before displaying any address or amount derived from it, the
+/// same way the ethereum signer warns before showing an unverified ERC20
+/// token.
+pub async fn warn_untrusted(params: &CustomParams) -> Result<(), Error> {
+    confirm::confirm(&confirm::Params {
+        title: "Unverified\ncoin",
+        body: &alloc::format!(
+            "Coin name and unit\n{} ({})\nare provided by the\napp and not verified\nby the BitBox02.",
+            params.name, params.unit,
+        ),
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await
+}
+
+/// Confirms an output's recipient address and amount on the trusted screen.
+/// `value_base_units` is formatted via [`CoinParams::format_amount`], i.e.
+/// scaled by `coin.decimals()`, rather than a hard-coded divisor, so this
+/// works for any coin `get` or [`CustomParams::from_pb`] can produce.
+///
+/// Not yet called from the BTC signing flow: this source tree doesn't
+/// contain a `bitcoin/sign.rs` or dispatcher to wire it into (see
+/// `params.rs` being the only file under `hww/api/bitcoin/`), so there is
+/// currently no real output-confirmation call site for it to replace.
+pub async fn verify_output(
+    coin: &dyn CoinParams,
+    address: &str,
+    value_base_units: u64,
+) -> Result<(), Error> {
+    crate::workflow::transaction::verify_recipient(address, &coin.format_amount(value_base_units))
+        .await
+}
+
+/// Common accessors shared by the built-in, audited [`Params`] and
+/// host-supplied [`CustomParams`], so address derivation and amount
+/// confirmation can be written once against either source.
+pub trait CoinParams {
+    fn bip44_coin(&self) -> u32;
+    fn base58_version_p2pkh(&self) -> u8;
+    fn base58_version_p2sh(&self) -> u8;
+    fn bech32_hrp(&self) -> &str;
+    fn name(&self) -> &str;
+    fn unit(&self) -> &str;
+    fn rbf_support(&self) -> bool;
+    /// Number of decimal places to format base-unit amounts with.
+    fn decimals(&self) -> u8;
+    /// Minimal non-dust output amount in base units, or `0` if not enforced.
+    fn dust_limit(&self) -> u64;
+
+    /// Formats `value` (in the coin's smallest base unit, e.g. satoshis) as
+    /// a human-readable decimal amount, e.g. `1234` at 8 decimals ->
+    /// `"0.00001234"`. This is the only sanctioned way to turn a base-unit
+    /// amount into a string for display on the trusted screen, so a coin's
+    /// `decimals` is never bypassed by a hard-coded divisor.
+    fn format_amount(&self, value: u64) -> alloc::string::String {
+        format_amount(value, self.decimals())
+    }
+}
+
+/// Formats `value` in the given number of `decimals`, trimming trailing
+/// fractional zeros (and the decimal point itself if the result is a whole
+/// number).
+fn format_amount(value: u64, decimals: u8) -> alloc::string::String {
+    let decimals = decimals as usize;
+    let mut digits = alloc::format!("{}", value);
+    if digits.len() <= decimals {
+        let mut padded = "0".repeat(decimals + 1 - digits.len());
+        padded.push_str(&digits);
+        digits = padded;
+    }
+    if decimals == 0 {
+        return digits;
+    }
+    let split_at = digits.len() - decimals;
+    let (int_part, frac_part) = digits.split_at(split_at);
+    let frac_part = frac_part.trim_end_matches('0');
+    if frac_part.is_empty() {
+        alloc::string::String::from(int_part)
+    } else {
+        alloc::format!("{}.{}", int_part, frac_part)
+    }
+}
+
+impl CoinParams for Params {
+    fn bip44_coin(&self) -> u32 {
+        self.bip44_coin
+    }
+    fn base58_version_p2pkh(&self) -> u8 {
+        self.base58_version_p2pkh
+    }
+    fn base58_version_p2sh(&self) -> u8 {
+        self.base58_version_p2sh
+    }
+    fn bech32_hrp(&self) -> &str {
+        self.bech32_hrp
+    }
+    fn name(&self) -> &str {
+        self.name
+    }
+    fn unit(&self) -> &str {
+        self.unit
+    }
+    fn rbf_support(&self) -> bool {
+        self.rbf_support
+    }
+    fn decimals(&self) -> u8 {
+        self.decimals
+    }
+    fn dust_limit(&self) -> u64 {
+        self.dust_limit
+    }
+}
+
+impl CoinParams for CustomParams {
+    fn bip44_coin(&self) -> u32 {
+        self.bip44_coin
+    }
+    fn base58_version_p2pkh(&self) -> u8 {
+        self.base58_version_p2pkh
+    }
+    fn base58_version_p2sh(&self) -> u8 {
+        self.base58_version_p2sh
+    }
+    fn bech32_hrp(&self) -> &str {
+        &self.bech32_hrp
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn unit(&self) -> &str {
+        &self.unit
+    }
+    fn rbf_support(&self) -> bool {
+        self.rbf_support
+    }
+    fn decimals(&self) -> u8 {
+        self.decimals
+    }
+    fn dust_limit(&self) -> u64 {
+        self.dust_limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::bech32;
+    use super::cashaddr;
+    use super::*;
+
+    #[test]
+    fn test_cashaddr_p2pkh_roundtrip() {
+        let hash = [0x11u8; 20];
+        let payload = cashaddr::encode("bitcoincash", cashaddr::AddressType::P2pkh, &hash).unwrap();
+        assert_eq!(
+            cashaddr::decode("bitcoincash", &payload),
+            Some((cashaddr::AddressType::P2pkh, hash.to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_cashaddr_p2sh_roundtrip() {
+        let hash = [0x22u8; 32];
+        let payload = cashaddr::encode("bchtest", cashaddr::AddressType::P2sh, &hash).unwrap();
+        assert_eq!(
+            cashaddr::decode("bchtest", &payload),
+            Some((cashaddr::AddressType::P2sh, hash.to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_cashaddr_rejects_wrong_prefix() {
+        let hash = [0x11u8; 20];
+        let payload = cashaddr::encode("bitcoincash", cashaddr::AddressType::P2pkh, &hash).unwrap();
+        assert_eq!(cashaddr::decode("bchtest", &payload), None);
+    }
+
+    #[test]
+    fn test_cashaddr_rejects_invalid_hash_length() {
+        assert_eq!(
+            cashaddr::encode("bitcoincash", cashaddr::AddressType::P2pkh, &[0u8; 21]),
+            None
+        );
+    }
+
+    fn valid_custom_params() -> CustomParams {
+        CustomParams {
+            bip44_coin: 3 + HARDENED,
+            base58_version_p2pkh: 0x1e,
+            base58_version_p2sh: 0x16,
+            bech32_hrp: "doge".into(),
+            name: "Dogecoin".into(),
+            unit: "DOGE".into(),
+            rbf_support: false,
+            decimals: 8,
+            dust_limit: 0,
+        }
+    }
+
+    #[test]
+    fn test_custom_params_rejects_excessive_decimals() {
+        let mut params = valid_custom_params();
+        params.decimals = 19;
+        assert_eq!(params.validate(), Err(CustomParamsError::DecimalsTooLarge));
+    }
+
+    #[test]
+    fn test_format_amount_btc_scale() {
+        assert_eq!(PARAMS_BTC.format_amount(0), "0");
+        assert_eq!(PARAMS_BTC.format_amount(1), "0.00000001");
+        assert_eq!(PARAMS_BTC.format_amount(100_000_000), "1");
+        assert_eq!(PARAMS_BTC.format_amount(123_456_789), "1.23456789");
+        assert_eq!(PARAMS_BTC.format_amount(150_000_000), "1.5");
+    }
+
+    #[test]
+    fn test_format_amount_zero_decimals() {
+        assert_eq!(format_amount(12345, 0), "12345");
+    }
+
+    #[test]
+    fn test_custom_params_valid() {
+        assert_eq!(valid_custom_params().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_custom_params_rejects_unhardened_coin() {
+        let mut params = valid_custom_params();
+        params.bip44_coin = 3;
+        assert_eq!(params.validate(), Err(CustomParamsError::CoinNotHardened));
+    }
+
+    #[test]
+    fn test_custom_params_rejects_conflicting_base58_versions() {
+        let mut params = valid_custom_params();
+        params.base58_version_p2sh = params.base58_version_p2pkh;
+        assert_eq!(
+            params.validate(),
+            Err(CustomParamsError::ConflictingBase58Versions)
+        );
+    }
+
+    #[test]
+    fn test_custom_params_rejects_empty_name() {
+        let mut params = valid_custom_params();
+        params.name = "".into();
+        assert_eq!(params.validate(), Err(CustomParamsError::EmptyNameOrUnit));
+    }
+
+    #[test]
+    fn test_custom_params_rejects_invalid_bech32_hrp() {
+        let mut params = valid_custom_params();
+        params.bech32_hrp = "DOGE".into();
+        assert_eq!(params.validate(), Err(CustomParamsError::InvalidBech32Hrp));
+    }
+
+    #[test]
+    fn test_custom_params_allows_empty_bech32_hrp() {
+        let mut params = valid_custom_params();
+        params.bech32_hrp = "".into();
+        assert_eq!(params.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_coin_params_trait_built_in_and_custom() {
+        fn unit_of(p: &dyn CoinParams) -> &str {
+            p.unit()
+        }
+        assert_eq!(unit_of(&PARAMS_BTC), "BTC");
+        assert_eq!(unit_of(&valid_custom_params()), "DOGE");
+    }
+
+    #[test]
+    fn test_bech32_v0_roundtrip() {
+        let program = [0u8; 20];
+        let address = bech32::encode("bc", 0, &program).unwrap();
+        assert_eq!(bech32::decode("bc", &address), Some((0, program.to_vec())));
+    }
+
+    #[test]
+    fn test_bech32m_v1_taproot_roundtrip() {
+        let program = [0x42u8; 32];
+        let address = bech32::encode("bc", 1, &program).unwrap();
+        assert_eq!(bech32::decode("bc", &address), Some((1, program.to_vec())));
+    }
+
+    #[test]
+    fn test_bech32_checksum_is_version_specific() {
+        // A v0 (bech32) checksum must not validate a v1 (bech32m) program, and vice versa.
+        let program = [0x42u8; 32];
+        let v1_address = bech32::encode("bc", 1, &program).unwrap();
+
+        // Flip the witness version byte from 1 to 0 without recomputing the checksum.
+        let mut chars: std::vec::Vec<char> = v1_address.chars().collect();
+        let data_start = v1_address.find('1').unwrap() + 1;
+        chars[data_start] = 'q'; // 'q' encodes 5-bit value 0 in CHARSET.
+        let mutated: std::string::String = chars.into_iter().collect();
+
+        assert_eq!(bech32::decode("bc", &mutated), None);
+
+        // BIP-350 test vectors: a v1 program with a bech32 checksum, and a v0
+        // program with a bech32m checksum. Both checksums are otherwise valid.
+        assert_eq!(
+            bech32::decode(
+                "bc",
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd"
+            ),
+            None
+        );
+        assert_eq!(
+            bech32::decode("bc", "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_bech32_rejects_wrong_hrp() {
+        let address = bech32::encode("bc", 0, &[0u8; 20]).unwrap();
+        assert_eq!(bech32::decode("tb", &address), None);
+    }
+
+    #[test]
+    fn test_bech32_rejects_invalid_program_length() {
+        // `encode` now enforces the same 20/32-byte witness program length as
+        // `decode`, so an invalid-length program can no longer be encoded at
+        // all (there's no address for `decode` to reject).
+        assert_eq!(bech32::encode("bc", 0, &[0u8; 19]), None);
+        assert_eq!(bech32::encode("bc", 1, &[0u8; 21]), None);
+    }
+
+    #[test]
+    fn test_bech32_rejects_data_part_with_no_program_bits() {
+        // "tb1dclvmr" has a checksum-only data part (6 chars) with no
+        // witness-version/program symbols; must be rejected, not panic.
+        assert_eq!(bech32::decode("tb", "tb1dclvmr"), None);
+    }
+
+    #[test]
+    fn test_bech32_rejects_mixed_case() {
+        // BIP-173: an address must be entirely lowercase or entirely
+        // uppercase; mixing cases must be rejected rather than normalized.
+        let address = bech32::encode("bc", 0, &[0u8; 20]).unwrap();
+        let mut mixed = address.clone();
+        // Flip the first data character (right after the "1" separator) to
+        // uppercase, leaving the rest (including the hrp) lowercase.
+        let data_start = address.find('1').unwrap() + 1;
+        let mut chars: std::vec::Vec<char> = mixed.chars().collect();
+        chars[data_start] = chars[data_start].to_ascii_uppercase();
+        mixed = chars.into_iter().collect();
+        assert_ne!(mixed, address);
+        assert_eq!(bech32::decode("bc", &mixed), None);
+    }
+
+    #[test]
+    fn test_encode_segwit_address_p2wpkh_and_p2tr() {
+        let program_v0 = [0u8; 20];
+        assert_eq!(
+            encode_segwit_address(BtcOutputType::P2wpkh, &PARAMS_BTC, &program_v0),
+            bech32::encode("bc", 0, &program_v0),
+        );
+
+        let program_v1 = [0x42u8; 32];
+        assert_eq!(
+            encode_segwit_address(BtcOutputType::P2tr, &PARAMS_BTC, &program_v1),
+            bech32::encode("bc", 1, &program_v1),
+        );
+    }
+
+    #[test]
+    fn test_encode_segwit_address_rejects_non_segwit_output_types() {
+        assert_eq!(
+            encode_segwit_address(BtcOutputType::P2pkh, &PARAMS_BTC, &[0u8; 20]),
+            None
+        );
+        assert_eq!(
+            encode_segwit_address(BtcOutputType::P2wpkhP2sh, &PARAMS_BTC, &[0u8; 20]),
+            None
+        );
+        assert_eq!(
+            encode_segwit_address(BtcOutputType::Unknown, &PARAMS_BTC, &[0u8; 20]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_custom_params_from_pb() {
+        let pb = pb::BtcCoinParams {
+            bip44_coin: 3 + HARDENED,
+            base58_version_p2pkh: 0x1e,
+            base58_version_p2sh: 0x16,
+            bech32_hrp: "doge".into(),
+            name: "Dogecoin".into(),
+            unit: "DOGE".into(),
+            rbf_support: false,
+            decimals: 8,
+            dust_limit: 0,
+        };
+        assert_eq!(CustomParams::from_pb(&pb), Ok(valid_custom_params()));
+    }
+
+    #[test]
+    fn test_custom_params_from_pb_rejects_out_of_range_fields() {
+        // A `decimals` of 274 would wrap to 18 under a plain `as u8` cast,
+        // sailing through `validate`'s `decimals > 18` check undetected.
+        assert_eq!(
+            CustomParams::from_pb(&pb::BtcCoinParams {
+                bip44_coin: 3 + HARDENED,
+                base58_version_p2pkh: 0x1e,
+                base58_version_p2sh: 0x16,
+                bech32_hrp: "doge".into(),
+                name: "Dogecoin".into(),
+                unit: "DOGE".into(),
+                rbf_support: false,
+                decimals: 274,
+                dust_limit: 0,
+            }),
+            Err(CustomParamsError::FieldOutOfRange)
+        );
+
+        assert_eq!(
+            CustomParams::from_pb(&pb::BtcCoinParams {
+                bip44_coin: 3 + HARDENED,
+                base58_version_p2pkh: 256,
+                base58_version_p2sh: 0x16,
+                bech32_hrp: "doge".into(),
+                name: "Dogecoin".into(),
+                unit: "DOGE".into(),
+                rbf_support: false,
+                decimals: 8,
+                dust_limit: 0,
+            }),
+            Err(CustomParamsError::FieldOutOfRange)
+        );
+
+        assert_eq!(
+            CustomParams::from_pb(&pb::BtcCoinParams {
+                bip44_coin: 3 + HARDENED,
+                base58_version_p2pkh: 0x1e,
+                base58_version_p2sh: 0x1_00,
+                bech32_hrp: "doge".into(),
+                name: "Dogecoin".into(),
+                unit: "DOGE".into(),
+                rbf_support: false,
+                decimals: 8,
+                dust_limit: 0,
+            }),
+            Err(CustomParamsError::FieldOutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_warn_untrusted() {
+        use crate::bb02_async::block_on;
+        use bitbox02::testing::{mock, Data};
+
+        let params = valid_custom_params();
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|confirm_params| {
+                assert_eq!(confirm_params.title, "Unverified\ncoin");
+                assert_eq!(
+                    confirm_params.body,
+                    "Coin name and unit\nDogecoin (DOGE)\nare provided by the\napp and not verified\nby the BitBox02."
+                );
+                true
+            })),
+            ..Default::default()
+        });
+        assert!(block_on(warn_untrusted(&params)).is_ok());
+    }
+
+    #[test]
+    fn test_verify_output_formats_via_decimals() {
+        use crate::bb02_async::block_on;
+        use bitbox02::testing::{mock, Data};
+
+        // 8 decimals (BTC): a hard-coded /1e8 divisor would happen to agree here.
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                assert_eq!(amount, "0.00000546");
+                assert_eq!(address, "bc1q...");
+                true
+            })),
+            ..Default::default()
+        });
+        assert!(block_on(verify_output(&PARAMS_BTC, "bc1q...", 546)).is_ok());
+
+        // A coin with a different decimal count than BTC's 8 would be
+        // formatted wrongly by a hard-coded /1e8 divisor, but not here.
+        let mut other = valid_custom_params();
+        other.decimals = 2;
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                assert_eq!(amount, "5.46");
+                assert_eq!(address, "D...");
+                true
+            })),
+            ..Default::default()
+        });
+        assert!(block_on(verify_output(&other, "D...", 546)).is_ok());
     }
 }
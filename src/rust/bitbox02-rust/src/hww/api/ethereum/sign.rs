@@ -31,10 +31,133 @@ use num_bigint::BigUint;
 // 1 ETH = 1e18 wei.
 const WEI_DECIMALS: usize = 18;
 
-/// Converts `recipient` to an array of 20 chars. If `recipient` is
-/// not exactly 20 elements, `InvalidInput` is returned.
-fn parse_recipient(recipient: &[u8]) -> Result<[u8; 20], Error> {
-    recipient.try_into().or(Err(Error::InvalidInput))
+/// EIP-2718 typed transaction envelope byte for EIP-2930 (access list)
+/// transactions.
+const TX_TYPE_EIP2930: u8 = 0x01;
+
+/// EIP-2718 typed transaction envelope byte for EIP-1559 (dynamic fee)
+/// transactions.
+const TX_TYPE_EIP1559: u8 = 0x02;
+
+mod keccak {
+    //! A from-scratch Keccak-256 (the original Keccak padding, as used by
+    //! Ethereum, not the later NIST SHA3-256 padding), used to compute
+    //! EIP-2718 typed transaction sighashes locally instead of delegating to
+    //! the legacy `sighash()` C helper, which only knows the 9-element
+    //! legacy/EIP-155 preimage.
+
+    use alloc::vec::Vec;
+
+    const RATE: usize = 136; // 1088 bits, for a 256-bit capacity/output.
+
+    const RC: [u64; 24] = [
+        0x0000000000000001,
+        0x0000000000008082,
+        0x800000000000808a,
+        0x8000000080008000,
+        0x000000000000808b,
+        0x0000000080000001,
+        0x8000000080008081,
+        0x8000000000008009,
+        0x000000000000008a,
+        0x0000000000000088,
+        0x0000000080008009,
+        0x000000008000000a,
+        0x000000008000808b,
+        0x800000000000008b,
+        0x8000000000008089,
+        0x8000000000008003,
+        0x8000000000008002,
+        0x8000000000000080,
+        0x000000000000800a,
+        0x800000008000000a,
+        0x8000000080008081,
+        0x8000000000008080,
+        0x0000000080000001,
+        0x8000000080008008,
+    ];
+
+    const ROT: [[u32; 5]; 5] = [
+        [0, 36, 3, 41, 18],
+        [1, 44, 10, 45, 2],
+        [62, 6, 43, 15, 61],
+        [28, 55, 25, 21, 56],
+        [27, 20, 39, 8, 14],
+    ];
+
+    fn keccak_f(state: &mut [[u64; 5]; 5]) {
+        for rc in RC.iter() {
+            // Theta
+            let mut c = [0u64; 5];
+            for x in 0..5 {
+                c[x] = state[x][0] ^ state[x][1] ^ state[x][2] ^ state[x][3] ^ state[x][4];
+            }
+            let mut d = [0u64; 5];
+            for x in 0..5 {
+                d[x] = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            }
+            for x in 0..5 {
+                for y in 0..5 {
+                    state[x][y] ^= d[x];
+                }
+            }
+            // Rho and pi
+            let mut b = [[0u64; 5]; 5];
+            for x in 0..5 {
+                for y in 0..5 {
+                    b[y][(2 * x + 3 * y) % 5] = state[x][y].rotate_left(ROT[x][y]);
+                }
+            }
+            // Chi
+            for x in 0..5 {
+                for y in 0..5 {
+                    state[x][y] = b[x][y] ^ ((!b[(x + 1) % 5][y]) & b[(x + 2) % 5][y]);
+                }
+            }
+            // Iota
+            state[0][0] ^= rc;
+        }
+    }
+
+    /// Computes the Keccak-256 digest of `data`.
+    pub fn keccak256(data: &[u8]) -> [u8; 32] {
+        let mut state = [[0u64; 5]; 5];
+
+        let mut padded: Vec<u8> = data.to_vec();
+        let pad_len = RATE - (padded.len() % RATE);
+        padded.extend(core::iter::repeat(0u8).take(pad_len));
+        let insert_at = padded.len() - pad_len;
+        padded[insert_at] = 0x01;
+        let last = padded.len() - 1;
+        padded[last] |= 0x80;
+
+        for block in padded.chunks(RATE) {
+            for (i, word) in block.chunks(8).enumerate() {
+                let mut buf = [0u8; 8];
+                buf[..word.len()].copy_from_slice(word);
+                state[i % 5][i / 5] ^= u64::from_le_bytes(buf);
+            }
+            keccak_f(&mut state);
+        }
+
+        let mut out = [0u8; 32];
+        for (i, chunk) in out.chunks_mut(8).enumerate() {
+            chunk.copy_from_slice(&state[i % 5][i / 5].to_le_bytes());
+        }
+        out
+    }
+}
+
+mod rlp;
+
+/// Parses `recipient` as a destination address. An empty `recipient` means
+/// contract creation and is returned as `None`. Any other length than empty
+/// or 20 is rejected as `InvalidInput`.
+fn parse_recipient(recipient: &[u8]) -> Result<Option<[u8; 20]>, Error> {
+    if recipient.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(recipient.try_into().or(Err(Error::InvalidInput))?))
 }
 
 /// Checks if the transaction is an ERC20 transaction.
@@ -43,41 +166,143 @@ fn parse_recipient(recipient: &[u8]) -> Result<[u8; 20], Error> {
 /// `<0xa9059cbb><32 bytes recipient><32 bytes value>`
 /// where recipient 20 bytes (zero padded to 32 bytes), and value is zero padded big endian number.
 /// On success, the 20 byte recipient and transaction value are returned.
-fn parse_erc20(request: &pb::EthSignRequest) -> Option<([u8; 20], BigUint)> {
-    if !request.value.is_empty() || request.data.len() != 68 {
-        return None;
+///
+/// `data` not starting with the ERC20 transfer selector is not an error: it is simply not an
+/// ERC20 transfer (`Ok(None)`), and is shown as a raw contract invocation instead. Once the
+/// selector is present, though, the call must be well-formed: wrong length or a non-zero-padded
+/// recipient word is rejected with `Error::InvalidInput`, mirroring `parse_recipient`, rather than
+/// silently falling back to the raw-data display.
+fn parse_erc20(request: &pb::EthSignRequest) -> Result<Option<([u8; 20], BigUint)>, Error> {
+    if request.data.len() < 4 || request.data[..4] != [0xa9, 0x05, 0x9c, 0xbb] {
+        return Ok(None);
     }
-    let (method, recipient, value) = (
-        &request.data[..4],
-        &request.data[4..36],
-        &request.data[36..68],
-    );
-    if method != [0xa9, 0x05, 0x9c, 0xbb] {
-        return None;
+    if !request.value.is_empty() || request.data.len() != 68 {
+        return Err(Error::InvalidInput);
     }
+    let (recipient, value) = (&request.data[4..36], &request.data[36..68]);
     // Recipient must be zero padded.
     if recipient[..12] != [0u8; 12] {
-        return None;
+        return Err(Error::InvalidInput);
     }
     // Transacted value can't be zero.
     if value == [0u8; 32] {
-        return None;
+        return Ok(None);
     }
-    Some((
+    Ok(Some((
         recipient[12..].try_into().unwrap(),
         BigUint::from_bytes_be(value),
-    ))
+    )))
 }
 
-// fee: gas limit * gas price:
-fn parse_fee<'a>(request: &pb::EthSignRequest, params: &'a Params) -> Amount<'a> {
-    let gas_price = BigUint::from_bytes_be(&request.gas_price);
+/// The EIP-2718 transaction envelope type, read from `EthSignRequest.tx_type`
+/// (see `messages/eth.proto`; 0 by default so existing legacy/EIP-155
+/// requests from older hosts are unaffected).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum TxType {
+    Legacy,
+    Eip2930,
+    Eip1559,
+}
+
+/// Decodes and validates `request.tx_type`. Unknown values are rejected so a
+/// future transaction type cannot silently fall through to the legacy
+/// preimage.
+fn decode_tx_type(request: &pb::EthSignRequest) -> Result<TxType, Error> {
+    match request.tx_type {
+        0 => Ok(TxType::Legacy),
+        1 => Ok(TxType::Eip2930),
+        2 => Ok(TxType::Eip1559),
+        _ => Err(Error::InvalidInput),
+    }
+}
+
+/// RLP-encodes an EIP-2930 access list: a list of `(address, [storage_keys])`
+/// entries, per https://eips.ethereum.org/EIPS/eip-2930. `access_list` (a
+/// repeated `EthAccessListEntry { address, storage_keys }`, see
+/// `messages/eth.proto`) is a field of `EthSignRequest`.
+fn encode_access_list(access_list: &[pb::EthAccessListEntry]) -> Vec<u8> {
+    let entries: Vec<Vec<u8>> = access_list
+        .iter()
+        .map(|entry| {
+            let storage_keys: Vec<Vec<u8>> = entry
+                .storage_keys
+                .iter()
+                .map(|key| rlp::encode_bytes(key))
+                .collect();
+            rlp::encode_list(&[
+                rlp::encode_bytes(&entry.address),
+                rlp::encode_list(&storage_keys),
+            ])
+        })
+        .collect();
+    rlp::encode_list(&entries)
+}
+
+// fee: for legacy and EIP-2930 transactions, gas limit * gas price. For
+// EIP-1559 transactions, the worst-case fee the user could pay: gas limit *
+// max_fee_per_gas.
+fn parse_fee<'a>(request: &pb::EthSignRequest, unit: &'a str, tx_type: TxType) -> Amount<'a> {
     let gas_limit = BigUint::from_bytes_be(&request.gas_limit);
+    let price = match tx_type {
+        TxType::Eip1559 => BigUint::from_bytes_be(&request.max_fee_per_gas),
+        TxType::Legacy | TxType::Eip2930 => BigUint::from_bytes_be(&request.gas_price),
+    };
     Amount {
-        unit: params.unit,
+        unit,
         decimals: WEI_DECIMALS,
-        value: gas_price.mul(gas_limit),
+        value: price.mul(gas_limit),
+    }
+}
+
+/// The chain to sign for: either the registered, audited [`Params`] for
+/// `request.coin`, or a host-supplied generic EVM chain identified by a
+/// non-empty `request.chain_id` (a big-endian integer; see
+/// `messages/eth.proto`, together with `network_name`/`unit`). This lets the
+/// host add support for an L2 or sidechain (e.g. "0.53 xDAI on Gnosis")
+/// without a firmware release, at the cost of `name`/`unit` being unverified
+/// by the device for such chains — every caller that signs against one must
+/// prominently display them as host-supplied, mirroring
+/// [`bitbox02::app_eth`]'s built-in `Params`.
+struct ChainParams<'a> {
+    chain_id: u64,
+    name: &'a str,
+    unit: &'a str,
+}
+
+/// Resolves the chain to sign for. If `request.chain_id` is empty, the
+/// registered `params` for `request.coin` are used unchanged. Otherwise,
+/// `request.chain_id` must be a non-zero integer that fits in 8 bytes (to
+/// encode as an RLP integer without ambiguity), and `request.network_name`/
+/// `request.unit` must be non-empty.
+fn resolve_chain<'a>(
+    request: &'a pb::EthSignRequest,
+    params: &'a Params,
+) -> Result<ChainParams<'a>, Error> {
+    if request.chain_id.is_empty() {
+        return Ok(ChainParams {
+            chain_id: params.chain_id,
+            name: params.name,
+            unit: params.unit,
+        });
     }
+    if request.chain_id.len() > 8
+        || matches!(request.chain_id.first(), Some(0))
+        || request.network_name.is_empty()
+        || request.unit.is_empty()
+    {
+        return Err(Error::InvalidInput);
+    }
+    let mut be_bytes = [0u8; 8];
+    be_bytes[8 - request.chain_id.len()..].copy_from_slice(&request.chain_id);
+    let chain_id = u64::from_be_bytes(be_bytes);
+    if chain_id == 0 {
+        return Err(Error::InvalidInput);
+    }
+    Ok(ChainParams {
+        chain_id,
+        name: &request.network_name,
+        unit: &request.unit,
+    })
 }
 
 /// Verifies an ERC20 transfer.
@@ -85,22 +310,26 @@ fn parse_fee<'a>(request: &pb::EthSignRequest, params: &'a Params) -> Amount<'a>
 /// If the ERC20 contract is known (stored in our list of supported ERC20 tokens), the token name,
 /// amount, recipient, total and fee are shown for confirmation.
 ///
-/// If the ERC20 token is unknown, only the recipient and fee can be shown. The token name and
-/// amount are displayed as "unknown". The amount is not known because we don't know the number of
-/// decimal places (specified in the ERC20 contract).
+/// If the ERC20 token is unknown, the host can still attest the token's symbol and decimals via
+/// `request.erc20_symbol`/`request.erc20_decimals` (see `messages/eth.proto`), in which case the
+/// amount is formatted and shown using that host-provided metadata, with an extra confirmation
+/// warning that it is unverified by the device. If the host doesn't supply them either
+/// (`erc20_symbol` empty), the token name and amount are displayed as "unknown", since we don't
+/// know the number of decimal places (specified in the ERC20 contract).
 async fn verify_erc20_transaction(
     request: &pb::EthSignRequest,
-    params: &Params,
+    chain: &ChainParams<'_>,
+    tx_type: TxType,
     erc20_recipient: [u8; 20],
     erc20_value: BigUint,
 ) -> Result<(), Error> {
-    let erc20_params = bitbox02::app_eth::erc20_params_get(
-        request.coin as _,
-        parse_recipient(&request.recipient)?,
-    );
-    let formatted_fee = parse_fee(request, params).format();
+    // An ERC20 transfer always calls into a contract, so the recipient
+    // (the contract address) can't be empty (contract creation).
+    let contract_address = parse_recipient(&request.recipient)?.ok_or(Error::InvalidInput)?;
+    let erc20_params = bitbox02::app_eth::erc20_params_get(request.coin as _, contract_address);
+    let formatted_fee = parse_fee(request, chain.unit, tx_type).format();
     let recipient_address = super::address::from_pubkey_hash(&erc20_recipient);
-    let (formatted_value, formatted_total) = match erc20_params {
+    let (formatted_value, formatted_total, host_provided_token_info) = match erc20_params {
         Some(erc20_params) => {
             let value = Amount {
                 unit: erc20_params.unit,
@@ -110,10 +339,28 @@ async fn verify_erc20_transaction(
             .format();
 
             // ERC20 token: fee has a different unit (ETH), so the total is just the value again.
-            (value.clone(), value.clone())
+            (value.clone(), value.clone(), false)
+        }
+        None if !request.erc20_symbol.is_empty() => {
+            let value = Amount {
+                unit: &request.erc20_symbol,
+                decimals: request.erc20_decimals as _,
+                value: erc20_value,
+            }
+            .format();
+            (value.clone(), value.clone(), true)
         }
-        None => ("Unknown token".into(), "Unknown amount".into()),
+        None => ("Unknown token".into(), "Unknown amount".into(), false),
     };
+    if host_provided_token_info {
+        confirm::confirm(&confirm::Params {
+            title: "Unverified\ntoken",
+            body: "Token name and amount\nare provided by the app\nand not verified by the\nBitBox02.",
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
     transaction::verify_recipient(&recipient_address, &formatted_value).await?;
     transaction::verify_total_fee(&formatted_total, &formatted_fee).await?;
     Ok(())
@@ -128,15 +375,15 @@ async fn verify_erc20_transaction(
 /// The transacted value, recipient address, total and fee are confirmed.
 async fn verify_standard_transaction(
     request: &pb::EthSignRequest,
-    params: &Params,
+    chain: &ChainParams<'_>,
+    tx_type: TxType,
+    recipient: [u8; 20],
 ) -> Result<(), Error> {
     if request.data.is_empty() && request.value.is_empty() {
         // Must transfer non-zero value, unless there is data (contract invocation).
         return Err(Error::InvalidInput);
     }
 
-    let recipient = parse_recipient(&request.recipient)?;
-
     if !request.data.is_empty() {
         confirm::confirm(&confirm::Params {
             title: "Unknown\ncontract",
@@ -166,15 +413,15 @@ async fn verify_standard_transaction(
 
     let address = super::address::from_pubkey_hash(&recipient);
     let amount = Amount {
-        unit: params.unit,
+        unit: chain.unit,
         decimals: WEI_DECIMALS,
         value: BigUint::from_bytes_be(&request.value),
     };
     transaction::verify_recipient(&address, &amount.format()).await?;
 
-    let fee = parse_fee(request, params);
+    let fee = parse_fee(request, chain.unit, tx_type);
     let total = Amount {
-        unit: params.unit,
+        unit: chain.unit,
         decimals: WEI_DECIMALS,
         value: amount.value.add(&fee.value),
     };
@@ -182,14 +429,200 @@ async fn verify_standard_transaction(
     Ok(())
 }
 
+/// EIP-3607: an address with deployed code can't be a legitimate transaction
+/// sender, so signing from one suggests an attack (e.g. a colliding
+/// deployment) rather than a genuine user request. The device can't query
+/// chain state itself, so this relies on `request.sender_has_code` (see
+/// `messages/eth.proto`), a host-supplied attestation that defaults to
+/// `false` for backwards compatibility with hosts that don't set it.
+async fn verify_sender_not_contract(request: &pb::EthSignRequest) -> Result<(), Error> {
+    if !request.sender_has_code {
+        return Ok(());
+    }
+    confirm::confirm(&confirm::Params {
+        title: "Error",
+        body: "Account is a\ncontract, can't sign",
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    Err(Error::InvalidInput)
+}
+
+/// Verifies a contract-creation transaction, i.e. one with an empty
+/// recipient. There is no recipient address to confirm, so instead the
+/// deployed value and the init code (shown as a scrollable hex blob,
+/// mirroring how `verify_standard_transaction` surfaces unknown contract
+/// data) are confirmed.
+async fn verify_contract_creation(
+    request: &pb::EthSignRequest,
+    chain: &ChainParams<'_>,
+    tx_type: TxType,
+) -> Result<(), Error> {
+    if request.data.is_empty() {
+        // Must supply init code in order to deploy a contract.
+        return Err(Error::InvalidInput);
+    }
+
+    confirm::confirm(&confirm::Params {
+        title: "Create\ncontract",
+        body: "You will be shown\nthe contract\ninit code.",
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    confirm::confirm(&confirm::Params {
+        title: "Create\ncontract",
+        body: "Only proceed if you\nunderstand exactly\nwhat the init code means.",
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    confirm::confirm(&confirm::Params {
+        title: "Init code",
+        body: &hex::encode(&request.data),
+        scrollable: true,
+        display_size: request.data.len(),
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+
+    let value = Amount {
+        unit: chain.unit,
+        decimals: WEI_DECIMALS,
+        value: BigUint::from_bytes_be(&request.value),
+    };
+    confirm::confirm(&confirm::Params {
+        title: "Create\ncontract",
+        body: &alloc::format!("Deploy with\n{}", value.format()),
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+
+    let fee = parse_fee(request, chain.unit, tx_type);
+    let total = Amount {
+        unit: chain.unit,
+        decimals: WEI_DECIMALS,
+        value: value.value.add(&fee.value),
+    };
+    transaction::verify_total_fee(&total.format(), &fee.format()).await?;
+    Ok(())
+}
+
+/// If the transaction carries an EIP-2930 access list, shows the number of
+/// addresses and storage slots it touches, so the user is aware that the
+/// transaction reads/writes state beyond the recipient, mirroring how
+/// `verify_standard_transaction` surfaces unknown contract data.
+async fn verify_access_list(request: &pb::EthSignRequest) -> Result<(), Error> {
+    if request.access_list.is_empty() {
+        return Ok(());
+    }
+    let num_addresses = request.access_list.len();
+    let num_slots: usize = request
+        .access_list
+        .iter()
+        .map(|entry| entry.storage_keys.len())
+        .sum();
+    confirm::confirm(&confirm::Params {
+        title: "Access list",
+        body: &alloc::format!(
+            "Accesses {} address(es),\n{} storage slot(s)",
+            num_addresses,
+            num_slots,
+        ),
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    Ok(())
+}
+
+/// Computes the EIP-2718 typed-envelope sighash for `tx_type` (EIP-2930 or
+/// EIP-1559): `keccak256(tx_type_byte || rlp(payload))`, where `payload` is
+/// the type's field list per EIP-2930/EIP-1559. Legacy (EIP-155)
+/// transactions are not typed envelopes and use `sighash()` instead; see
+/// `process`.
+fn sighash_typed(
+    request: &pb::EthSignRequest,
+    recipient: &[u8],
+    chain_id: u64,
+    tx_type: TxType,
+) -> [u8; 32] {
+    let (tx_type_byte, payload) = match tx_type {
+        TxType::Eip2930 => (
+            TX_TYPE_EIP2930,
+            rlp::encode_list(&[
+                rlp::encode_uint(&chain_id.to_be_bytes()),
+                rlp::encode_uint(&request.nonce),
+                rlp::encode_uint(&request.gas_price),
+                rlp::encode_uint(&request.gas_limit),
+                rlp::encode_bytes(recipient),
+                rlp::encode_uint(&request.value),
+                rlp::encode_bytes(&request.data),
+                encode_access_list(&request.access_list),
+            ]),
+        ),
+        TxType::Eip1559 => (
+            TX_TYPE_EIP1559,
+            rlp::encode_list(&[
+                rlp::encode_uint(&chain_id.to_be_bytes()),
+                rlp::encode_uint(&request.nonce),
+                rlp::encode_uint(&request.max_priority_fee_per_gas),
+                rlp::encode_uint(&request.max_fee_per_gas),
+                rlp::encode_uint(&request.gas_limit),
+                rlp::encode_bytes(recipient),
+                rlp::encode_uint(&request.value),
+                rlp::encode_bytes(&request.data),
+                encode_access_list(&request.access_list),
+            ]),
+        ),
+        TxType::Legacy => unreachable!("legacy transactions use sighash(), not sighash_typed()"),
+    };
+    let mut preimage = alloc::vec![tx_type_byte];
+    preimage.extend(payload);
+    keccak::keccak256(&preimage)
+}
+
+/// Computes the legacy (non-EIP-2718) EIP-155 sighash for a contract-creation
+/// transaction: `keccak256(rlp([nonce, gas_price, gas_limit, "", value, data,
+/// chain_id, 0, 0]))`. Used only for contract creation (empty `to`); the
+/// external `sighash()` C helper only supports a 20-byte recipient.
+fn sighash_legacy_create(request: &pb::EthSignRequest, chain_id: u64) -> [u8; 32] {
+    let payload = rlp::encode_list(&[
+        rlp::encode_uint(&request.nonce),
+        rlp::encode_uint(&request.gas_price),
+        rlp::encode_uint(&request.gas_limit),
+        rlp::encode_bytes(&[]),
+        rlp::encode_uint(&request.value),
+        rlp::encode_bytes(&request.data),
+        rlp::encode_uint(&chain_id.to_be_bytes()),
+        rlp::encode_uint(&[]),
+        rlp::encode_uint(&[]),
+    ]);
+    keccak::keccak256(&payload)
+}
+
 /// Verify and sign an Ethereum transaction.
 pub async fn process(request: &pb::EthSignRequest) -> Result<Response, Error> {
     let params = params_get(request.coin as _).ok_or(Error::InvalidInput)?;
 
+    // `is_valid_keypath_address` only checks the BIP44 structure (coin type
+    // `60'`, the Ethereum SLIP-44 coin type shared by EVM-compatible chains),
+    // not which registered coin/chain is being signed for, so it applies
+    // equally to a host-supplied generic chain (`request.chain_id`
+    // non-empty). The "unusual keypath" warning is likewise always shown;
+    // for a generic chain, `params.name` (e.g. "Ethereum") is still the
+    // right coin-type label to warn about, since the chain's own name isn't
+    // resolved/verified until `resolve_chain` below.
     if !super::keypath::is_valid_keypath_address(&request.keypath) {
         return Err(Error::InvalidInput);
     }
     super::keypath::warn_unusual_keypath(&params, params.name, &request.keypath).await?;
+    verify_sender_not_contract(request).await?;
+
+    let tx_type = decode_tx_type(request)?;
 
     // Size limits.
     if request.nonce.len() > 16
@@ -197,46 +630,108 @@ pub async fn process(request: &pb::EthSignRequest) -> Result<Response, Error> {
         || request.gas_limit.len() > 16
         || request.value.len() > 32
         || request.data.len() > 1024
+        || request.max_priority_fee_per_gas.len() > 32
+        || request.max_fee_per_gas.len() > 32
+        || request.access_list.len() > 100
+        || request
+            .access_list
+            .iter()
+            .any(|entry| entry.address.len() != 20 || entry.storage_keys.len() > 100)
+        || request.erc20_symbol.len() > 32
+        || request.chain_id.len() > 8
+        || request.network_name.len() > 32
+        || request.unit.len() > 32
     {
         return Err(Error::InvalidInput);
     }
 
+    let chain = resolve_chain(request, &params)?;
+    if !request.chain_id.is_empty() {
+        // `chain.name`/`chain.unit` come from the host for a generic chain
+        // (see `resolve_chain`/`ChainParams`) and aren't verified by the
+        // device, so every subsequent confirmation that shows them (the
+        // recipient/fee/total screens below, via `chain.unit`) is preceded
+        // by this warning naming them explicitly.
+        confirm::confirm(&confirm::Params {
+            title: "Unverified\nnetwork",
+            body: &alloc::format!(
+                "Network name and unit\n{} ({})\nare provided by the\napp and not verified\nby the BitBox02.",
+                chain.name, chain.unit,
+            ),
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
     // No zero prefix in the big endian numbers.
     if let [0, ..] = &request.nonce[..] {
         return Err(Error::InvalidInput);
     }
-    if let [0, ..] = &request.gas_price[..] {
-        return Err(Error::InvalidInput);
-    }
     if let [0, ..] = &request.gas_limit[..] {
         return Err(Error::InvalidInput);
     }
     if let [0, ..] = &request.value[..] {
         return Err(Error::InvalidInput);
     }
+    match tx_type {
+        TxType::Eip1559 => {
+            if let [0, ..] = &request.max_priority_fee_per_gas[..] {
+                return Err(Error::InvalidInput);
+            }
+            if let [0, ..] = &request.max_fee_per_gas[..] {
+                return Err(Error::InvalidInput);
+            }
+        }
+        TxType::Legacy | TxType::Eip2930 => {
+            if let [0, ..] = &request.gas_price[..] {
+                return Err(Error::InvalidInput);
+            }
+        }
+    }
 
     let recipient = parse_recipient(&request.recipient)?;
-    if recipient == [0; 20] {
-        // Reserved for contract creation.
+    if recipient == Some([0; 20]) {
+        // The zero address is reserved to mean contract creation (an empty
+        // recipient), and can't be used as an explicit destination.
         return Err(Error::InvalidInput);
     }
 
-    if let Some((erc20_recipient, erc20_value)) = parse_erc20(request) {
-        verify_erc20_transaction(request, &params, erc20_recipient, erc20_value).await?;
-    } else {
-        verify_standard_transaction(request, &params).await?;
+    verify_access_list(request).await?;
+    match recipient {
+        None => verify_contract_creation(request, &chain, tx_type).await?,
+        Some(recipient) => {
+            if let Some((erc20_recipient, erc20_value)) = parse_erc20(request)? {
+                verify_erc20_transaction(request, &chain, tx_type, erc20_recipient, erc20_value)
+                    .await?;
+            } else {
+                verify_standard_transaction(request, &chain, tx_type, recipient).await?;
+            }
+        }
     }
 
-    let hash = sighash(SighashParams {
-        nonce: &request.nonce,
-        gas_price: &request.gas_price,
-        gas_limit: &request.gas_limit,
-        recipient: &recipient,
-        value: &request.value,
-        data: &request.data,
-        chain_id: params.chain_id,
-    })
-    .or(Err(Error::InvalidInput))?;
+    let recipient_bytes: &[u8] = match &recipient {
+        Some(recipient) => recipient,
+        None => &[],
+    };
+    let hash = match tx_type {
+        TxType::Eip2930 | TxType::Eip1559 => {
+            sighash_typed(request, recipient_bytes, chain.chain_id, tx_type)
+        }
+        TxType::Legacy => match recipient {
+            Some(recipient) => sighash(SighashParams {
+                nonce: &request.nonce,
+                gas_price: &request.gas_price,
+                gas_limit: &request.gas_limit,
+                recipient: &recipient,
+                value: &request.value,
+                data: &request.data,
+                chain_id: chain.chain_id,
+            })
+            .or(Err(Error::InvalidInput))?,
+            None => sighash_legacy_create(request, chain.chain_id),
+        },
+    };
 
     let host_nonce = match request.host_nonce_commitment {
         // Engage in the anti-klepto protocol if the host sends a host nonce commitment.
@@ -265,6 +760,196 @@ pub async fn process(request: &pb::EthSignRequest) -> Result<Response, Error> {
     Ok(Response::Sign(pb::EthSignResponse { signature }))
 }
 
+/// Computes `hashStruct(message)` per EIP-712:
+/// `keccak256(keccak256(type_string) || encoded_field_1 || ... ||
+/// encoded_field_n)`, where `type_string` is the `encodeType` string for the
+/// message's primary type (e.g. `"Mail(address to,uint256 value)"`) and each
+/// `encoded_value` is the already ABI-encoded 32-byte word for that field, as
+/// computed host-side (this signer does not implement a general-purpose
+/// EIP-712 ABI encoder for arbitrary structs/arrays, which `encodeData` would
+/// otherwise require). See https://eips.ethereum.org/EIPS/eip-712.
+fn hash_struct(type_string: &str, fields: &[pb::Eip712Field]) -> [u8; 32] {
+    let type_hash = keccak::keccak256(type_string.as_bytes());
+    let mut preimage: Vec<u8> = type_hash.to_vec();
+    for field in fields {
+        preimage.extend_from_slice(&field.encoded_value);
+    }
+    keccak::keccak256(&preimage)
+}
+
+/// The ABI type of an EIP-712 struct field (`Eip712Field.field_type`, see
+/// `messages/eth.proto`), used to derive the on-device
+/// confirmation string for a field directly from `encoded_value` (the word
+/// that is actually hashed into `hashStruct`/signed) rather than trusting
+/// the host-supplied `value` display string outright: without this, a host
+/// could show a friendly `value` (e.g. a known address, "1 ETH") while
+/// signing a completely different `encoded_value`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Eip712FieldType {
+    /// A 20-byte address, right-aligned/zero-padded to 32 bytes.
+    Address,
+    /// An unsigned integer, encoded as a 32-byte big-endian word.
+    Uint,
+    /// `bool`, encoded as the 32-byte word `0` or `1`.
+    Bool,
+    /// An opaque 32-byte value (e.g. `bytes32`), shown as hex.
+    Bytes32,
+    /// A dynamic `string`. Per EIP-712's `encodeData`, the encoded word is
+    /// `keccak256(value)`, not the value itself, so `value` can only be
+    /// trusted once the device has confirmed it hashes to `encoded_value`.
+    String,
+    /// A dynamic `bytes`, hashed the same way as `String` but interpreted
+    /// as raw (hex-encoded-in-`value`) bytes rather than UTF-8 text.
+    Bytes,
+}
+
+fn decode_field_type(field_type: &str) -> Result<Eip712FieldType, Error> {
+    match field_type {
+        "address" => Ok(Eip712FieldType::Address),
+        "uint" => Ok(Eip712FieldType::Uint),
+        "bool" => Ok(Eip712FieldType::Bool),
+        "bytes32" => Ok(Eip712FieldType::Bytes32),
+        "string" => Ok(Eip712FieldType::String),
+        "bytes" => Ok(Eip712FieldType::Bytes),
+        _ => Err(Error::InvalidInput),
+    }
+}
+
+/// Derives the string to show the user for one EIP-712 field, strictly from
+/// `field.encoded_value` and `field.field_type`. For the atomic types
+/// (`address`/`uint`/`bool`/`bytes32`), the display is computed directly
+/// from the 32-byte signed word. For the dynamic types (`string`/`bytes`),
+/// the host-supplied `field.value` is displayed only after confirming
+/// `keccak256(value)` equals `encoded_value`, i.e. that `value` is what was
+/// actually committed to by the signed word, per EIP-712's `encodeData`.
+fn format_field_value(field: &pb::Eip712Field) -> Result<alloc::string::String, Error> {
+    Ok(match decode_field_type(&field.field_type)? {
+        Eip712FieldType::Address => {
+            if field.encoded_value[..12] != [0u8; 12] {
+                return Err(Error::InvalidInput);
+            }
+            let address: [u8; 20] = field.encoded_value[12..].try_into().unwrap();
+            super::address::from_pubkey_hash(&address)
+        }
+        Eip712FieldType::Uint => BigUint::from_bytes_be(&field.encoded_value).to_str_radix(10),
+        Eip712FieldType::Bool => {
+            if field.encoded_value == [0u8; 32] {
+                "false".into()
+            } else if field.encoded_value[..31] == [0u8; 31] && field.encoded_value[31] == 1 {
+                "true".into()
+            } else {
+                return Err(Error::InvalidInput);
+            }
+        }
+        Eip712FieldType::Bytes32 => alloc::format!("0x{}", hex::encode(field.encoded_value)),
+        Eip712FieldType::String => {
+            if keccak::keccak256(field.value.as_bytes()) != field.encoded_value {
+                return Err(Error::InvalidInput);
+            }
+            field.value.clone()
+        }
+        Eip712FieldType::Bytes => {
+            // Unlike `String`, `value` here is the hex encoding of the raw
+            // bytes, not the bytes themselves: `encodeData` hashes the raw
+            // bytes (`keccak256(value)` per EIP-712), not the UTF-8 bytes of
+            // their hex representation.
+            let raw = hex::decode(&field.value).or(Err(Error::InvalidInput))?;
+            if keccak::keccak256(&raw) != field.encoded_value {
+                return Err(Error::InvalidInput);
+            }
+            alloc::format!("0x{}", field.value)
+        }
+    })
+}
+
+/// Verify and sign an EIP-712 typed-data message.
+///
+/// `request.domain_separator` and the type tree for the message are
+/// supplied by the host, since recomputing `domainSeparator` and walking an
+/// arbitrary, recursively-defined type tree isn't practical on this
+/// constrained signer. Instead, the device independently recomputes
+/// `hashStruct(message)` from `request.type_string`/`request.fields` and
+/// checks it against the host-claimed `request.message_hash`, so the set of
+/// fields and their encoded values can't silently diverge from what is
+/// actually signed; any mismatch is rejected with `Error::InvalidInput`.
+/// Every field is then shown to the user by name and a value derived from
+/// `field.encoded_value` (see [`format_field_value`] — the host-supplied
+/// `field.value` is never displayed unverified, since it is otherwise
+/// disconnected from what gets signed), reusing the same abort semantics as
+/// the rest of this module, so a rejection yields `Error::UserAbort`, before
+/// signing `keccak256(0x1901 || domain_separator || hashStruct(message))`.
+///
+/// (`EthSignTypedMessageRequest` and `Eip712Field` are defined in
+/// `messages/eth.proto`.)
+///
+/// Not yet reachable from any host request: this tree has no
+/// `hww/api/mod.rs` or other request dispatcher to route an incoming
+/// `EthSignTypedMessageRequest` here (the same is true of this module's
+/// plain-transaction [`process`] - neither has a caller in this slice of the
+/// repo). Wiring a dispatch table entry is out of this function's scope; it
+/// belongs wherever the host request envelope is matched against message
+/// type, which isn't present here.
+pub async fn process_typed_message(
+    request: &pb::EthSignTypedMessageRequest,
+) -> Result<Response, Error> {
+    if !super::keypath::is_valid_keypath_address(&request.keypath) {
+        return Err(Error::InvalidInput);
+    }
+
+    // Size limits, mirroring the ones in `process()`.
+    if request.type_string.len() > 256
+        || request.fields.len() > 32
+        || request.fields.iter().any(|field| {
+            field.name.len() > 64 || field.value.len() > 256 || field.field_type.len() > 16
+        })
+    {
+        return Err(Error::InvalidInput);
+    }
+
+    if hash_struct(&request.type_string, &request.fields) != request.message_hash {
+        return Err(Error::InvalidInput);
+    }
+
+    for field in request.fields.iter() {
+        let value = format_field_value(field)?;
+        confirm::confirm(&confirm::Params {
+            title: &field.name,
+            body: &value,
+            scrollable: true,
+            display_size: value.len(),
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    let mut preimage: Vec<u8> = alloc::vec![0x19, 0x01];
+    preimage.extend_from_slice(&request.domain_separator);
+    preimage.extend_from_slice(&request.message_hash);
+    let hash = keccak::keccak256(&preimage);
+
+    let host_nonce = match request.host_nonce_commitment {
+        Some(pb::AntiKleptoHostNonceCommitment { ref commitment }) => {
+            let signer_commitment = keystore::secp256k1_nonce_commit(
+                &request.keypath,
+                &hash,
+                commitment
+                    .as_slice()
+                    .try_into()
+                    .or(Err(Error::InvalidInput))?,
+            )?;
+            super::antiklepto_get_host_nonce(signer_commitment).await?
+        }
+        None => [0; 32],
+    };
+    let sign_result = keystore::secp256k1_sign(&request.keypath, &hash, &host_nonce)?;
+
+    let mut signature: Vec<u8> = sign_result.signature.to_vec();
+    signature.push(sign_result.recid);
+
+    Ok(Response::Sign(pb::EthSignResponse { signature }))
+}
+
 #[cfg(test)]
 mod tests {
     extern crate std;
@@ -279,9 +964,12 @@ mod tests {
     pub fn test_parse_recipient() {
         assert_eq!(
             parse_recipient(b"01234567890123456789"),
-            Ok(*b"01234567890123456789"),
+            Ok(Some(*b"01234567890123456789")),
         );
 
+        // Empty recipient means contract creation.
+        assert_eq!(parse_recipient(b""), Ok(None));
+
         assert_eq!(
             parse_recipient(b"0123456789012345678"),
             Err(Error::InvalidInput),
@@ -293,88 +981,668 @@ mod tests {
     }
 
     #[test]
-    pub fn test_parse_erc20() {
-        let valid_data =
-            b"\xa9\x05\x9c\xbb\0\0\0\0\0\0\0\0\0\0\0\0abcdefghijklmnopqrst\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x55\0\0\0\xff";
+    pub fn test_parse_erc20() {
+        let valid_data =
+            b"\xa9\x05\x9c\xbb\0\0\0\0\0\0\0\0\0\0\0\0abcdefghijklmnopqrst\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x55\0\0\0\xff";
+        assert_eq!(
+            parse_erc20(&pb::EthSignRequest {
+                data: valid_data.to_vec(),
+                ..Default::default()
+            }),
+            Ok(Some((*b"abcdefghijklmnopqrst", 365072220415u64.into())))
+        );
+
+        // Data not starting with the ERC20 transfer selector is simply not
+        // an ERC20 transfer (shown as a raw contract invocation instead).
+        assert_eq!(parse_erc20(&pb::EthSignRequest::default()), Ok(None));
+
+        // ETH value must be 0 when transacting ERC20.
+        assert_eq!(
+            parse_erc20(&pb::EthSignRequest {
+                value: vec![0],
+                data: valid_data.to_vec(),
+                ..Default::default()
+            }),
+            Err(Error::InvalidInput)
+        );
+
+        // Invalid method (first byte): not an ERC20 call at all.
+        let invalid_data = b"\xa8\x05\x9c\xbb\0\0\0\0\0\0\0\0\0\0\0\0abcdefghijklmnopqrst\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\xff";
+        assert_eq!(
+            parse_erc20(&pb::EthSignRequest {
+                data: invalid_data.to_vec(),
+                ..Default::default()
+            }),
+            Ok(None)
+        );
+
+        // Recipient too long (not zero padded): right selector, malformed
+        // call, rejected rather than shown as raw data.
+        let invalid_data = b"\xa9\x05\x9c\xbb\0\0\0\0\0\0\0\0\0\0\0babcdefghijklmnopqrst\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\xff";
+        assert_eq!(
+            parse_erc20(&pb::EthSignRequest {
+                data: invalid_data.to_vec(),
+                ..Default::default()
+            }),
+            Err(Error::InvalidInput)
+        );
+
+        // Value can't be zero: well-formed but degenerate, not a transfer.
+        let invalid_data = b"\xa9\x05\x9c\xbb\0\0\0\0\0\0\0\0\0\0\0\0abcdefghijklmnopqrst\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x00";
+        assert_eq!(
+            parse_erc20(&pb::EthSignRequest {
+                data: invalid_data.to_vec(),
+                ..Default::default()
+            }),
+            Ok(None)
+        );
+
+        // Right selector, but the calldata is the wrong length: malformed,
+        // rejected with InvalidInput rather than falling back to a raw
+        // contract-data display, mirroring parse_recipient's rejections.
+        assert_eq!(
+            parse_erc20(&pb::EthSignRequest {
+                data: valid_data[..valid_data.len() - 1].to_vec(),
+                ..Default::default()
+            }),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn test_keccak256_empty_and_abc() {
+        // Known-answer tests (Keccak-256, not the later NIST SHA3-256, which
+        // pads differently and would give a different digest).
+        assert_eq!(
+            hex::encode(keccak::keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex::encode(keccak::keccak256(b"abc")),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+    }
+
+    #[test]
+    fn test_decode_tx_type_and_fee() {
+        let legacy = pb::EthSignRequest {
+            tx_type: 0,
+            gas_price: b"\x01".to_vec(),
+            gas_limit: b"\x02".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(decode_tx_type(&legacy), Ok(TxType::Legacy));
+
+        let eip2930 = pb::EthSignRequest {
+            tx_type: 1,
+            gas_price: b"\x01".to_vec(),
+            gas_limit: b"\x02".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(decode_tx_type(&eip2930), Ok(TxType::Eip2930));
+
+        let eip1559 = pb::EthSignRequest {
+            tx_type: 2,
+            max_priority_fee_per_gas: b"\x01".to_vec(),
+            max_fee_per_gas: b"\x03".to_vec(),
+            gas_limit: b"\x02".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(decode_tx_type(&eip1559), Ok(TxType::Eip1559));
+
+        let unknown = pb::EthSignRequest {
+            tx_type: 3,
+            ..Default::default()
+        };
+        assert_eq!(decode_tx_type(&unknown), Err(Error::InvalidInput));
+    }
+
+    #[test]
+    fn test_resolve_chain() {
+        let params = params_get(pb::EthCoin::Eth as _).unwrap();
+
+        // No host-supplied chain_id: falls back to the registered coin's params.
+        let request = pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            ..Default::default()
+        };
+        let chain = resolve_chain(&request, &params).unwrap();
+        assert_eq!(chain.chain_id, params.chain_id);
+        assert_eq!(chain.name, params.name);
+        assert_eq!(chain.unit, params.unit);
+
+        // Host-supplied generic chain.
+        let request = pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            chain_id: b"\x64".to_vec(), // 100, Gnosis Chain's chain_id.
+            network_name: "Gnosis".into(),
+            unit: "xDAI".into(),
+            ..Default::default()
+        };
+        let chain = resolve_chain(&request, &params).unwrap();
+        assert_eq!(chain.chain_id, 100);
+        assert_eq!(chain.name, "Gnosis");
+        assert_eq!(chain.unit, "xDAI");
+
+        // chain_id of zero is not a valid chain.
+        let request = pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            chain_id: b"\x00".to_vec(),
+            network_name: "Gnosis".into(),
+            unit: "xDAI".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_chain(&request, &params).unwrap_err(),
+            Error::InvalidInput
+        );
+
+        // Doesn't fit a u64.
+        let request = pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            chain_id: alloc::vec![1; 9],
+            network_name: "Gnosis".into(),
+            unit: "xDAI".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_chain(&request, &params).unwrap_err(),
+            Error::InvalidInput
+        );
+
+        // Missing network_name/unit.
+        let request = pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            chain_id: b"\x64".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_chain(&request, &params).unwrap_err(),
+            Error::InvalidInput
+        );
+    }
+
+    /// For EIP-1559, the fee shown to the user is the worst case the account
+    /// could be charged (`gas_limit * max_fee_per_gas`), ignoring
+    /// `max_priority_fee_per_gas`, which only affects how that fee is split
+    /// between the miner tip and the base fee.
+    #[test]
+    fn test_parse_fee_eip1559_worst_case() {
+        let params = params_get(pb::EthCoin::Eth as _).unwrap();
+        let request = pb::EthSignRequest {
+            tx_type: 2,
+            gas_limit: b"\x52\x08".to_vec(),
+            max_fee_per_gas: b"\x01\x65\xa0\xbc\x00".to_vec(),
+            max_priority_fee_per_gas: b"\x01".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_fee(&request, params.unit, TxType::Eip1559).format(),
+            "0.000126 ETH"
+        );
+    }
+
+    /// For EIP-2930 (like legacy), the fee is simply `gas_limit * gas_price`
+    /// (there is no separate max-fee/priority-fee split).
+    #[test]
+    fn test_parse_fee_eip2930() {
+        let params = params_get(pb::EthCoin::Eth as _).unwrap();
+        let request = pb::EthSignRequest {
+            tx_type: 1,
+            gas_limit: b"\x52\x08".to_vec(),
+            gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_fee(&request, params.unit, TxType::Eip2930).format(),
+            "0.000126 ETH"
+        );
+    }
+
+    /// `verify_erc20_transaction` is only ever reached from `process` when
+    /// `request.recipient` (the contract address) is non-empty, but it
+    /// guards against an empty recipient itself too, rather than trusting
+    /// the caller: an empty recipient means contract creation, which can
+    /// never be an ERC20 transfer.
+    #[test]
+    fn test_verify_erc20_transaction_rejects_empty_recipient() {
+        let chain = ChainParams {
+            chain_id: 1,
+            name: "Ethereum",
+            unit: "ETH",
+        };
+        let request = pb::EthSignRequest {
+            recipient: b"".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(
+            block_on(verify_erc20_transaction(
+                &request,
+                &chain,
+                TxType::Legacy,
+                [0x11; 20],
+                BigUint::from_bytes_be(b"\x01"),
+            )),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn test_encode_access_list() {
+        let access_list = alloc::vec![pb::EthAccessListEntry {
+            address: b"\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa".to_vec(),
+            storage_keys: alloc::vec![b"\x01".to_vec()],
+        }];
+
+        assert_eq!(encode_access_list(&[]), alloc::vec![0xc0]);
+        let encoded = encode_access_list(&access_list);
+        // A one-entry list: outer list header, entry list header, 20-byte
+        // address (with its own length prefix), and a one-element storage
+        // keys list containing a single-byte key (which encodes as itself,
+        // since it is below 0x80).
+        assert_eq!(encoded.len(), 1 + 1 + 1 + 20 + 1 + 1);
+    }
+
+    #[test]
+    fn test_sighash_typed() {
+        let recipient = [0x11; 20];
+        let request = pb::EthSignRequest {
+            nonce: b"\x01".to_vec(),
+            gas_price: b"\x02".to_vec(),
+            gas_limit: b"\x03".to_vec(),
+            value: b"\x04".to_vec(),
+            max_priority_fee_per_gas: b"\x05".to_vec(),
+            max_fee_per_gas: b"\x06".to_vec(),
+            ..Default::default()
+        };
+
+        let eip2930_hash = sighash_typed(&request, &recipient, 1, TxType::Eip2930);
+        let eip1559_hash = sighash_typed(&request, &recipient, 1, TxType::Eip1559);
+        // Same fields, different envelope type/payload: hashes must differ.
+        assert_ne!(eip2930_hash, eip1559_hash);
+
+        // Changing the chain ID changes the preimage, and thus the hash.
+        let eip1559_hash_other_chain = sighash_typed(&request, &recipient, 2, TxType::Eip1559);
+        assert_ne!(eip1559_hash, eip1559_hash_other_chain);
+    }
+
+    /// Pinned against an independently computed EIP-2718/EIP-1559 sighash
+    /// (`keccak256(0x02 || rlp([chain_id, nonce, max_priority_fee_per_gas,
+    /// max_fee_per_gas, gas_limit, recipient, value, data, access_list]))`),
+    /// not just "hashes differ from each other".
+    #[test]
+    fn test_sighash_typed_eip1559_pinned() {
+        let recipient = [
+            0x04, 0xf2, 0x64, 0xcf, 0x34, 0x44, 0x03, 0x13, 0xb4, 0xa0, 0x19, 0x2a, 0x35, 0x28,
+            0x14, 0xfb, 0xe9, 0x27, 0xb8, 0x85,
+        ];
+        let request = pb::EthSignRequest {
+            nonce: b"\x1f\xdc".to_vec(),
+            gas_limit: b"\x52\x08".to_vec(),
+            value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
+            data: b"".to_vec(),
+            max_priority_fee_per_gas: b"\x01".to_vec(),
+            max_fee_per_gas: b"\x01\x65\xa0\xbc\x00".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(
+            hex::encode(sighash_typed(&request, &recipient, 1, TxType::Eip1559)),
+            "682a0c83cc199cc75b12c532268354093d79571c4910c8799f6931be6325b600"
+        );
+    }
+
+    /// Standard ETH transaction with no data field.
+    #[test]
+    pub fn test_process_standard_transaction() {
+        let _guard = MUTEX.lock().unwrap();
+
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                assert_eq!(amount, "0.530564 ETH");
+                assert_eq!(address, "0x04F264Cf34440313B4A0192A352814FBe927b885");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee| {
+                assert_eq!(total, "0.53069 ETH");
+                assert_eq!(fee, "0.000126 ETH");
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process(&pb::EthSignRequest {
+                coin: pb::EthCoin::Eth as _,
+                keypath: KEYPATH.to_vec(),
+                nonce: b"\x1f\xdc".to_vec(),
+                gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
+                gas_limit: b"\x52\x08".to_vec(),
+                recipient: b"\x04\xf2\x64\xcf\x34\x44\x03\x13\xb4\xa0\x19\x2a\x35\x28\x14\xfb\xe9\x27\xb8\x85".to_vec(),
+                value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
+                data: b"".to_vec(),
+                host_nonce_commitment: None,
+                ..Default::default()
+            })),
+            Ok(Response::Sign(pb::EthSignResponse {
+                signature: b"\xc3\xae\x24\xc1\x67\xe2\x16\xcf\xb7\x5c\x72\xb5\xe0\x3e\xf9\x7a\xcc\x2b\x60\x7f\x3a\xcf\x63\x86\x5f\x80\x96\x0f\x76\xf6\x56\x47\x0f\x8e\x23\xf1\xd2\x78\x8f\xb0\x07\x0e\x28\xc2\xa5\xc8\xaa\xf1\x5b\x5d\xbf\x30\xb4\x09\x07\xff\x6c\x50\x68\xfd\xcb\xc1\x1a\x2d\x00"
+                    .to_vec()
+            }))
+        );
+    }
+
+    /// Same transaction as [`test_process_standard_transaction`], but as an
+    /// EIP-1559 (type-2) transaction: `max_fee_per_gas` takes the place of
+    /// `gas_price`, so the confirmed fee/total are identical, but the
+    /// signature differs (it signs a different, typed-envelope preimage) and
+    /// so isn't pinned here; see `test_sighash_typed_eip1559_pinned` for the
+    /// pinned preimage hash.
+    #[test]
+    pub fn test_process_eip1559_transaction() {
+        let _guard = MUTEX.lock().unwrap();
+
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                assert_eq!(amount, "0.530564 ETH");
+                assert_eq!(address, "0x04F264Cf34440313B4A0192A352814FBe927b885");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee| {
+                assert_eq!(total, "0.53069 ETH");
+                assert_eq!(fee, "0.000126 ETH");
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let result = block_on(process(&pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            keypath: KEYPATH.to_vec(),
+            tx_type: 2,
+            nonce: b"\x1f\xdc".to_vec(),
+            max_priority_fee_per_gas: b"\x01".to_vec(),
+            max_fee_per_gas: b"\x01\x65\xa0\xbc\x00".to_vec(),
+            gas_limit: b"\x52\x08".to_vec(),
+            recipient: b"\x04\xf2\x64\xcf\x34\x44\x03\x13\xb4\xa0\x19\x2a\x35\x28\x14\xfb\xe9\x27\xb8\x85".to_vec(),
+            value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
+            data: b"".to_vec(),
+            host_nonce_commitment: None,
+            ..Default::default()
+        }));
+        if let Response::Sign(response) = result.unwrap() {
+            assert_eq!(response.signature.len(), 65);
+        } else {
+            panic!("expected Response::Sign");
+        }
+    }
+
+    /// Pinned against an independently computed EIP-2718/EIP-2930 sighash
+    /// (`keccak256(0x01 || rlp([chain_id, nonce, gas_price, gas_limit,
+    /// recipient, value, data, access_list]))`), not just "hashes differ
+    /// from each other".
+    #[test]
+    fn test_sighash_typed_eip2930_pinned() {
+        let recipient = [
+            0x04, 0xf2, 0x64, 0xcf, 0x34, 0x44, 0x03, 0x13, 0xb4, 0xa0, 0x19, 0x2a, 0x35, 0x28,
+            0x14, 0xfb, 0xe9, 0x27, 0xb8, 0x85,
+        ];
+        let request = pb::EthSignRequest {
+            nonce: b"\x1f\xdc".to_vec(),
+            gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
+            gas_limit: b"\x52\x08".to_vec(),
+            value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
+            data: b"".to_vec(),
+            access_list: alloc::vec![pb::EthAccessListEntry {
+                address: alloc::vec![0xaa; 20],
+                storage_keys: alloc::vec![b"\x01".to_vec()],
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            hex::encode(sighash_typed(&request, &recipient, 1, TxType::Eip2930)),
+            "df793b51256985acaf205a0f5ce3a33a8316dd9c109a242f264fd0ec57c19b31"
+        );
+    }
+
+    /// Same transaction as [`test_process_standard_transaction`], but as an
+    /// EIP-2930 transaction carrying a one-entry access list: confirms the
+    /// "Access list" screen is shown in addition to the usual
+    /// recipient/fee screens.
+    #[test]
+    pub fn test_process_eip2930_transaction() {
+        let _guard = MUTEX.lock().unwrap();
+
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "Access list");
+                assert_eq!(params.body, "Accesses 1 address(es),\n1 storage slot(s)");
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                assert_eq!(amount, "0.530564 ETH");
+                assert_eq!(address, "0x04F264Cf34440313B4A0192A352814FBe927b885");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee| {
+                assert_eq!(total, "0.53069 ETH");
+                assert_eq!(fee, "0.000126 ETH");
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let result = block_on(process(&pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            keypath: KEYPATH.to_vec(),
+            tx_type: 1,
+            nonce: b"\x1f\xdc".to_vec(),
+            gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
+            gas_limit: b"\x52\x08".to_vec(),
+            recipient: b"\x04\xf2\x64\xcf\x34\x44\x03\x13\xb4\xa0\x19\x2a\x35\x28\x14\xfb\xe9\x27\xb8\x85".to_vec(),
+            value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
+            data: b"".to_vec(),
+            access_list: alloc::vec![pb::EthAccessListEntry {
+                address: alloc::vec![0xaa; 20],
+                storage_keys: alloc::vec![b"\x01".to_vec()],
+            }],
+            host_nonce_commitment: None,
+            ..Default::default()
+        }));
+        if let Response::Sign(response) = result.unwrap() {
+            assert_eq!(response.signature.len(), 65);
+        } else {
+            panic!("expected Response::Sign");
+        }
+    }
+
+    /// The EIP-2718 envelope dispatcher (`decode_tx_type`, called from
+    /// `process`) must reject a `tx_type` it doesn't know about end-to-end,
+    /// not just at the unit level: a future type must never silently fall
+    /// through to the legacy preimage.
+    #[test]
+    pub fn test_process_rejects_unknown_tx_type() {
+        let _guard = MUTEX.lock().unwrap();
+
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_| panic!("no confirmation should be shown"))),
+            ui_transaction_address_create: Some(Box::new(|_, _| {
+                panic!("no confirmation should be shown")
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process(&pb::EthSignRequest {
+                coin: pb::EthCoin::Eth as _,
+                keypath: KEYPATH.to_vec(),
+                tx_type: 3,
+                nonce: b"\x1f\xdc".to_vec(),
+                gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
+                gas_limit: b"\x52\x08".to_vec(),
+                recipient: b"\x04\xf2\x64\xcf\x34\x44\x03\x13\xb4\xa0\x19\x2a\x35\x28\x14\xfb\xe9\x27\xb8\x85".to_vec(),
+                value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
+                data: b"".to_vec(),
+                host_nonce_commitment: None,
+                ..Default::default()
+            })),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    /// Pinned against an independently computed legacy (EIP-155)
+    /// contract-creation sighash (`keccak256(rlp([nonce, gas_price,
+    /// gas_limit, "", value, data, chain_id, 0, 0]))`; the empty string in
+    /// the recipient slot, and the trailing `0, 0` EIP-155 placeholders for
+    /// `r`/`s`), not just "hashes differ from each other".
+    #[test]
+    fn test_sighash_legacy_create_pinned() {
+        let request = pb::EthSignRequest {
+            nonce: b"".to_vec(),
+            gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
+            gas_limit: b"\x52\x08".to_vec(),
+            value: b"".to_vec(),
+            data: b"\x60\x00\x60\x00".to_vec(),
+            ..Default::default()
+        };
         assert_eq!(
-            parse_erc20(&pb::EthSignRequest {
-                data: valid_data.to_vec(),
-                ..Default::default()
-            }),
-            Some((*b"abcdefghijklmnopqrst", 365072220415u64.into()))
+            hex::encode(sighash_legacy_create(&request, 1)),
+            "d0bf57967bc9e001594933ab40f4745b0293c2339a907aff1e10c4274881c668"
         );
+    }
 
-        // ETH value must be 0 when transacting ERC20.
-        assert!(parse_erc20(&pb::EthSignRequest {
-            value: vec![0],
-            data: valid_data.to_vec(),
-            ..Default::default()
-        })
-        .is_none());
+    /// Legacy contract-creation transaction (empty recipient): confirms the
+    /// init-code/deploy-value screens and signs against
+    /// `sighash_legacy_create`, end-to-end through `process`.
+    #[test]
+    pub fn test_process_contract_creation() {
+        let _guard = MUTEX.lock().unwrap();
 
-        // Invalid method (first byte)
-        let invalid_data = b"\xa8\x05\x9c\xbb\0\0\0\0\0\0\0\0\0\0\0\0abcdefghijklmnopqrst\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\xff";
-        assert!(parse_erc20(&pb::EthSignRequest {
-            data: invalid_data.to_vec(),
-            ..Default::default()
-        })
-        .is_none());
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
 
-        // Recipient too long (not zero padded)
-        let invalid_data = b"\xa9\x05\x9c\xbb\0\0\0\0\0\0\0\0\0\0\0babcdefghijklmnopqrst\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\xff";
-        assert!(parse_erc20(&pb::EthSignRequest {
-            data: invalid_data.to_vec(),
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Create\ncontract");
+                        assert_eq!(params.body, "You will be shown\nthe contract\ninit code.");
+                    }
+                    2 => {
+                        assert_eq!(params.title, "Create\ncontract");
+                        assert_eq!(
+                            params.body,
+                            "Only proceed if you\nunderstand exactly\nwhat the init code means."
+                        );
+                    }
+                    3 => {
+                        assert_eq!(params.title, "Init code");
+                        assert_eq!(params.body, "60006000");
+                    }
+                    4 => {
+                        assert_eq!(params.title, "Create\ncontract");
+                        assert_eq!(params.body, "Deploy with\n0 ETH");
+                    }
+                    _ => panic!("too many user confirmations"),
+                }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee| {
+                assert_eq!(total, "0.000126 ETH");
+                assert_eq!(fee, "0.000126 ETH");
+                true
+            })),
             ..Default::default()
-        })
-        .is_none());
-
-        // Value can't be zero
-        let invalid_data = b"\xa9\x05\x9c\xbb\0\0\0\0\0\0\0\0\0\0\0\0abcdefghijklmnopqrst\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x00";
-        assert!(parse_erc20(&pb::EthSignRequest {
-            data: invalid_data.to_vec(),
+        });
+        mock_unlocked();
+        let result = block_on(process(&pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            keypath: KEYPATH.to_vec(),
+            nonce: b"".to_vec(),
+            gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
+            gas_limit: b"\x52\x08".to_vec(),
+            recipient: b"".to_vec(),
+            value: b"".to_vec(),
+            data: b"\x60\x00\x60\x00".to_vec(),
+            host_nonce_commitment: None,
             ..Default::default()
-        })
-        .is_none());
+        }));
+        if let Response::Sign(response) = result.unwrap() {
+            assert_eq!(response.signature.len(), 65);
+        } else {
+            panic!("expected Response::Sign");
+        }
     }
 
-    /// Standard ETH transaction with no data field.
+    /// A host-supplied generic chain (`request.chain_id` non-empty) must
+    /// show `chain.name` (not just `chain.unit`) as an unverified,
+    /// host-supplied value before any recipient/fee confirmation.
     #[test]
-    pub fn test_process_standard_transaction() {
+    pub fn test_process_generic_chain_warns_with_network_name() {
         let _guard = MUTEX.lock().unwrap();
 
         const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
 
+        static mut CONFIRM_COUNTER: u32 = 0;
         mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Unverified\nnetwork");
+                        assert_eq!(
+                            params.body,
+                            "Network name and unit\nGnosis (xDAI)\nare provided by the\napp and not verified\nby the BitBox02."
+                        );
+                        true
+                    }
+                    _ => panic!("too many user confirmations"),
+                }
+            })),
             ui_transaction_address_create: Some(Box::new(|amount, address| {
-                assert_eq!(amount, "0.530564 ETH");
+                assert_eq!(amount, "0.530564 xDAI");
                 assert_eq!(address, "0x04F264Cf34440313B4A0192A352814FBe927b885");
                 true
             })),
             ui_transaction_fee_create: Some(Box::new(|total, fee| {
-                assert_eq!(total, "0.53069 ETH");
-                assert_eq!(fee, "0.000126 ETH");
+                assert_eq!(total, "0.53069 xDAI");
+                assert_eq!(fee, "0.000126 xDAI");
                 true
             })),
             ..Default::default()
         });
         mock_unlocked();
-        assert_eq!(
-            block_on(process(&pb::EthSignRequest {
-                coin: pb::EthCoin::Eth as _,
-                keypath: KEYPATH.to_vec(),
-                nonce: b"\x1f\xdc".to_vec(),
-                gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
-                gas_limit: b"\x52\x08".to_vec(),
-                recipient: b"\x04\xf2\x64\xcf\x34\x44\x03\x13\xb4\xa0\x19\x2a\x35\x28\x14\xfb\xe9\x27\xb8\x85".to_vec(),
-                value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
-                data: b"".to_vec(),
-                host_nonce_commitment: None,
-            })),
-            Ok(Response::Sign(pb::EthSignResponse {
-                signature: b"\xc3\xae\x24\xc1\x67\xe2\x16\xcf\xb7\x5c\x72\xb5\xe0\x3e\xf9\x7a\xcc\x2b\x60\x7f\x3a\xcf\x63\x86\x5f\x80\x96\x0f\x76\xf6\x56\x47\x0f\x8e\x23\xf1\xd2\x78\x8f\xb0\x07\x0e\x28\xc2\xa5\xc8\xaa\xf1\x5b\x5d\xbf\x30\xb4\x09\x07\xff\x6c\x50\x68\xfd\xcb\xc1\x1a\x2d\x00"
-                    .to_vec()
-            }))
-        );
+        let result = block_on(process(&pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            keypath: KEYPATH.to_vec(),
+            chain_id: b"\x64".to_vec(),
+            network_name: "Gnosis".into(),
+            unit: "xDAI".into(),
+            nonce: b"\x1f\xdc".to_vec(),
+            gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
+            gas_limit: b"\x52\x08".to_vec(),
+            recipient: b"\x04\xf2\x64\xcf\x34\x44\x03\x13\xb4\xa0\x19\x2a\x35\x28\x14\xfb\xe9\x27\xb8\x85".to_vec(),
+            value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
+            data: b"".to_vec(),
+            host_nonce_commitment: None,
+            ..Default::default()
+        }));
+        if let Response::Sign(response) = result.unwrap() {
+            assert_eq!(response.signature.len(), 65);
+        } else {
+            panic!("expected Response::Sign");
+        }
     }
 
     /// Standard ETH transaction on an unusual keypath (Ropsten on mainnet keypath)
@@ -425,11 +1693,47 @@ mod tests {
             value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
             data: b"".to_vec(),
             host_nonce_commitment: None,
+            ..Default::default()
         }))
         .unwrap();
         assert_eq!(unsafe { CONFIRM_COUNTER }, 1);
     }
 
+    /// EIP-3607: a host attestation that the sender address has deployed
+    /// code aborts signing, regardless of the rest of the transaction.
+    #[test]
+    pub fn test_process_sender_has_code() {
+        let _guard = MUTEX.lock().unwrap();
+
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "Error");
+                assert_eq!(params.body, "Account is a\ncontract, can't sign");
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process(&pb::EthSignRequest {
+                coin: pb::EthCoin::Eth as _,
+                keypath: KEYPATH.to_vec(),
+                nonce: b"\x1f\xdc".to_vec(),
+                gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
+                gas_limit: b"\x52\x08".to_vec(),
+                recipient: b"\x04\xf2\x64\xcf\x34\x44\x03\x13\xb4\xa0\x19\x2a\x35\x28\x14\xfb\xe9\x27\xb8\x85".to_vec(),
+                value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
+                data: b"".to_vec(),
+                host_nonce_commitment: None,
+                sender_has_code: true,
+                ..Default::default()
+            })),
+            Err(Error::InvalidInput)
+        );
+    }
+
     /// Standard ETH transaction with an unknown data field.
     #[test]
     pub fn test_process_standard_transaction_with_data() {
@@ -477,6 +1781,7 @@ mod tests {
                 value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
                 data: b"foo bar".to_vec(),
                 host_nonce_commitment: None,
+                ..Default::default()
             })),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\x7d\x3f\x37\x13\xe3\xcf\x10\x82\x79\x1d\x5c\x0f\xc6\x8e\xc2\x9e\xaf\xf5\xe1\xee\x84\x67\xa8\xec\x54\x7d\xc7\x96\xe8\x5a\x79\x04\x2b\x7c\x01\x69\x2f\xb7\x2f\x55\x76\xab\x50\xdc\xaa\x62\x1a\xd1\xee\xab\xd9\x97\x59\x73\xb8\x62\x56\xf4\x0c\x6f\x85\x50\xef\x44\x00"
@@ -518,6 +1823,7 @@ mod tests {
                 value: b"".to_vec(),
                 data: b"\xa9\x05\x9c\xbb\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xe6\xce\x0a\x09\x2a\x99\x70\x0c\xd4\xcc\xcc\xbb\x1f\xed\xc3\x9c\xf5\x3e\x63\x30\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x03\x65\xc0\x40".to_vec(),
                 host_nonce_commitment: None,
+                ..Default::default()
             })),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\x67\x4e\x9a\x01\x70\xee\xe0\xca\x8c\x40\x6e\xc9\xa7\xdf\x2e\x3a\x6b\xdd\x17\x9c\xf6\x93\x85\x80\x0e\x1f\xd3\x78\xe7\xcf\xb1\x9c\x4d\x55\x16\x2c\x54\x7b\x04\xd1\x81\x8e\x43\x90\x16\x91\xae\xc9\x88\xef\x75\xcd\x67\xd9\xbb\x30\x1d\x14\x90\x2f\xd6\xe6\x92\x92\x01"
@@ -558,6 +1864,7 @@ mod tests {
                 value: b"".to_vec(),
                 data: b"\xa9\x05\x9c\xbb\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x85\x7b\x3d\x96\x9e\xac\xb7\x75\xa9\xf7\x9c\xab\xc6\x2e\xc4\xbb\x1d\x1c\xd6\x0e\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x98\xa6\x3c\xbe\xb8\x59\xd0\x27\xb0".to_vec(),
                 host_nonce_commitment: None,
+                ..Default::default()
             })),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\xec\x6e\x53\x0c\x8e\xe2\x54\x34\xfc\x44\x0e\x9a\xc0\xf8\x88\xe9\xc6\x3c\xf0\x7e\xbc\xf1\xc2\xf8\xa8\x3e\x2e\x8c\x39\x83\x2c\x55\x15\x12\x71\x6f\x6e\x1a\x8b\x66\xce\x38\x11\xa7\x26\xbc\xb2\x44\x66\x4e\xf2\x6f\x98\xee\x35\xc0\xc9\xdb\x4c\xaa\xb0\x73\x98\x56\x00"
@@ -566,6 +1873,63 @@ mod tests {
         );
     }
 
+    /// An unknown ERC20 transaction where the host supplies the token symbol
+    /// and decimals: the amount is formatted using that metadata, and an
+    /// extra "unverified" warning is shown before the recipient/amount.
+    #[test]
+    pub fn test_verify_erc20_transaction_host_provided_token_info() {
+        let _guard = MUTEX.lock().unwrap();
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(unsafe { CONFIRM_COUNTER }, 0);
+                unsafe { CONFIRM_COUNTER += 1 };
+                assert_eq!(params.title, "Unverified\ntoken");
+                assert_eq!(
+                    params.body,
+                    "Token name and amount\nare provided by the app\nand not verified by the\nBitBox02."
+                );
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                assert_eq!(amount, "57 FOO");
+                assert_eq!(address, "0x857B3D969eAcB775a9f79cabc62Ec4bB1D1cd60e");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee| {
+                assert_eq!(total, "57 FOO");
+                assert_eq!(fee, "0.000067973 ETH");
+                true
+            })),
+            ..Default::default()
+        });
+        let params = params_get(pb::EthCoin::Eth as _).unwrap();
+        let chain = ChainParams {
+            chain_id: params.chain_id,
+            name: params.name,
+            unit: params.unit,
+        };
+        let request = pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            gas_price: b"\x3b\x9a\xca\x00".to_vec(),
+            gas_limit: b"\x01\x09\x85".to_vec(),
+            recipient: b"\x9c\x23\xd6\x7a\xea\x7b\x95\xd8\x09\x42\xe3\x83\x6b\xcd\xf7\xe7\x08\xa7\x47\xc1".to_vec(),
+            erc20_symbol: "FOO".into(),
+            erc20_decimals: 6,
+            ..Default::default()
+        };
+        assert!(block_on(verify_erc20_transaction(
+            &request,
+            &chain,
+            TxType::Legacy,
+            *b"\x85\x7b\x3d\x96\x9e\xac\xb7\x75\xa9\xf7\x9c\xab\xc6\x2e\xc4\xbb\x1d\x1c\xd6\x0e",
+            57_000_000u64.into(),
+        ))
+        .is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 1);
+    }
+
     #[test]
     pub fn test_process_unhappy() {
         let _guard = MUTEX.lock().unwrap();
@@ -582,6 +1946,7 @@ mod tests {
             value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
             data: b"".to_vec(),
             host_nonce_commitment: None,
+            ..Default::default()
         };
 
         {
@@ -614,6 +1979,21 @@ mod tests {
             );
         }
 
+        {
+            // invalid keypath (wrong coin part), still rejected for a
+            // host-supplied generic chain: keypath validation isn't bypassed
+            // just because `chain_id` is set.
+            let mut invalid_request = valid_request.clone();
+            invalid_request.keypath = vec![44 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0];
+            invalid_request.chain_id = b"\x64".to_vec();
+            invalid_request.network_name = "Gnosis".into();
+            invalid_request.unit = "xDAI".into();
+            assert_eq!(
+                block_on(process(&invalid_request)),
+                Err(Error::InvalidInput)
+            );
+        }
+
         {
             // data too long
             let mut invalid_request = valid_request.clone();
@@ -683,4 +2063,274 @@ mod tests {
             assert_eq!(block_on(process(&valid_request)), Err(Error::Generic));
         }
     }
+
+    #[test]
+    fn test_hash_struct() {
+        let fields = alloc::vec![
+            pb::Eip712Field {
+                name: "to".into(),
+                value: "0x0000000000000000000000000000000000000001".into(),
+                field_type: "address".into(),
+                encoded_value: {
+                    let mut v = [0u8; 32];
+                    v[31] = 0x01;
+                    v
+                },
+            },
+            pb::Eip712Field {
+                name: "value".into(),
+                value: "42".into(),
+                field_type: "uint".into(),
+                encoded_value: {
+                    let mut v = [0u8; 32];
+                    v[31] = 42;
+                    v
+                },
+            },
+        ];
+        let h1 = hash_struct("Mail(address to,uint256 value)", &fields);
+
+        // Changing the type string changes the hash: the type hash is part
+        // of the preimage, so a different (or subtly malformed) type tree
+        // can't collide with the honest one.
+        let h2 = hash_struct("Mail(address to,uint256 amount)", &fields);
+        assert_ne!(h1, h2);
+
+        // Changing a field's encoded value changes the hash.
+        let mut other_fields = fields.clone();
+        other_fields[1].encoded_value[31] = 43;
+        let h3 = hash_struct("Mail(address to,uint256 value)", &other_fields);
+        assert_ne!(h1, h3);
+    }
+
+    /// The confirmation string is derived from `encoded_value`, not the
+    /// host-supplied `value`: a bogus `value` is ignored for the atomic
+    /// types, and rejected outright for the dynamic types where `value` is
+    /// supposed to prove itself via `keccak256(value) == encoded_value`.
+    #[test]
+    fn test_format_field_value() {
+        // uint: value is derived straight from the word, ignoring `value`.
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "value".into(),
+                value: "this is not 42".into(),
+                field_type: "uint".into(),
+                encoded_value: {
+                    let mut v = [0u8; 32];
+                    v[31] = 42;
+                    v
+                },
+            }),
+            Ok("42".into())
+        );
+
+        // address: value is derived straight from the word, ignoring
+        // `value`, which could otherwise claim to be a different address.
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "to".into(),
+                value: "0xAttackerControlledAddress".into(),
+                field_type: "address".into(),
+                encoded_value: {
+                    let mut v = [0u8; 32];
+                    v[31] = 0x01;
+                    v
+                },
+            }),
+            Ok(super::super::address::from_pubkey_hash(&{
+                let mut a = [0u8; 20];
+                a[19] = 0x01;
+                a
+            }))
+        );
+
+        // address: non-zero-padded high bytes can't be a valid address word.
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "to".into(),
+                value: "whatever".into(),
+                field_type: "address".into(),
+                encoded_value: [0xff; 32],
+            }),
+            Err(Error::InvalidInput)
+        );
+
+        // bool: only the words for false/true are accepted.
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "flag".into(),
+                value: "true".into(),
+                field_type: "bool".into(),
+                encoded_value: [0u8; 32],
+            }),
+            Ok("false".into())
+        );
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "flag".into(),
+                value: "false".into(),
+                field_type: "bool".into(),
+                encoded_value: {
+                    let mut v = [0u8; 32];
+                    v[31] = 1;
+                    v
+                },
+            }),
+            Ok("true".into())
+        );
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "flag".into(),
+                value: "true".into(),
+                field_type: "bool".into(),
+                encoded_value: [0x02; 32],
+            }),
+            Err(Error::InvalidInput)
+        );
+
+        // string: the claimed `value` must actually hash to `encoded_value`
+        // (EIP-712's `encodeData` for dynamic types) - a mismatched `value`
+        // is rejected rather than displayed.
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "memo".into(),
+                value: "hello".into(),
+                field_type: "string".into(),
+                encoded_value: keccak::keccak256(b"hello"),
+            }),
+            Ok("hello".into())
+        );
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "memo".into(),
+                value: "goodbye".into(),
+                field_type: "string".into(),
+                encoded_value: keccak::keccak256(b"hello"),
+            }),
+            Err(Error::InvalidInput)
+        );
+
+        // bytes: `value` is the *hex encoding* of the raw bytes, not the
+        // bytes themselves - `encodeData` hashes the raw bytes. A host
+        // setting `encoded_value = keccak256(raw_bytes)` and
+        // `value = hex::encode(raw_bytes)` must be accepted and displayed as
+        // `0x<value>`.
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "data".into(),
+                value: hex::encode(b"hello"),
+                field_type: "bytes".into(),
+                encoded_value: keccak::keccak256(b"hello"),
+            }),
+            Ok(alloc::format!("0x{}", hex::encode(b"hello")))
+        );
+        // A `value` that hashes correctly as a raw UTF-8 string, but not as
+        // the bytes its hex encoding decodes to, must be rejected.
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "data".into(),
+                value: "hello".into(),
+                field_type: "bytes".into(),
+                encoded_value: keccak::keccak256(b"hello"),
+            }),
+            Err(Error::InvalidInput)
+        );
+        // `value` that isn't valid hex at all is rejected outright.
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "data".into(),
+                value: "not hex!".into(),
+                field_type: "bytes".into(),
+                encoded_value: [0u8; 32],
+            }),
+            Err(Error::InvalidInput)
+        );
+
+        // Unknown field type.
+        assert_eq!(
+            format_field_value(&pb::Eip712Field {
+                name: "x".into(),
+                value: "x".into(),
+                field_type: "tuple".into(),
+                encoded_value: [0u8; 32],
+            }),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    /// A message hash that doesn't match the recomputed `hashStruct` of the
+    /// declared type tree/fields is rejected before any field is shown to
+    /// the user.
+    #[test]
+    fn test_process_typed_message_hash_mismatch() {
+        let _guard = MUTEX.lock().unwrap();
+
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_| panic!("no field should be shown"))),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_typed_message(&pb::EthSignTypedMessageRequest {
+                coin: pb::EthCoin::Eth as _,
+                keypath: KEYPATH.to_vec(),
+                domain_separator: [0x11; 32],
+                message_hash: [0x22; 32],
+                type_string: "Mail(address to,uint256 value)".into(),
+                fields: alloc::vec![pb::Eip712Field {
+                    name: "to".into(),
+                    value: "0x0000000000000000000000000000000000000001".into(),
+                    field_type: "address".into(),
+                    encoded_value: [0x01; 32],
+                }],
+                host_nonce_commitment: None,
+            })),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    /// Rejecting any field aborts the whole signing request.
+    #[test]
+    fn test_process_typed_message_user_abort() {
+        let _guard = MUTEX.lock().unwrap();
+
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+
+        let fields = alloc::vec![pb::Eip712Field {
+            name: "value".into(),
+            value: "ignored, not a uint256".into(),
+            field_type: "uint".into(),
+            encoded_value: {
+                let mut v = [0u8; 32];
+                v[31] = 42;
+                v
+            },
+        }];
+        let type_string: alloc::string::String = "Mail(uint256 value)".into();
+        let message_hash = hash_struct(&type_string, &fields);
+
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                // The displayed value is derived from `encoded_value`
+                // ("42"), not the (bogus) host-supplied `value` string.
+                assert_eq!(params.title, "value");
+                assert_eq!(params.body, "42");
+                false
+            })),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_typed_message(&pb::EthSignTypedMessageRequest {
+                coin: pb::EthCoin::Eth as _,
+                keypath: KEYPATH.to_vec(),
+                domain_separator: [0x11; 32],
+                message_hash,
+                type_string,
+                fields,
+                host_nonce_commitment: None,
+            })),
+            Err(Error::UserAbort)
+        );
+    }
 }